@@ -22,19 +22,27 @@ extern crate libraytracing;
 
 use criterion::Criterion;
 use libraytracing::camera::equilinear_camera::*;
+use libraytracing::camera::filter::BoxFilter;
+use libraytracing::camera::sampler::RegularSampler;
 use libraytracing::camera::Camera;
 use libraytracing::error::Error;
 use libraytracing::obj::FileParser;
 use libraytracing::shader::mirror_shader::MirrorShader;
 use libraytracing::shader::*;
 use libraytracing::world::World;
-use libraytracing::world::light::Light;
+use libraytracing::world::light::PointLight;
 use libraytracing::world::plane::*;
 use libraytracing::world::sphere::*;
 use na::Vector3;
 use std::env;
 use std::time::{SystemTime, UNIX_EPOCH};
 use libraytracing::storage::primitive_storage::PrimitiveStorage;
+use libraytracing::storage::bv_storage::{Builder, SahParameters};
+use libraytracing::storage::collector::Collector;
+use libraytracing::storage::Bounded;
+use libraytracing::world::instance::Instance;
+use na::Matrix4;
+use std::sync::Arc;
 
 fn parsing_ducky(c: &mut Criterion) {
     c.bench_function("parse ducky.obj",
@@ -56,7 +64,7 @@ fn ducky_primitive_storage(c: &mut Criterion) {
                 let mut elements = file_parser.elements;
                 
                 let mut lights = Vec::new();
-                lights.push(Light::new(700.0, 50.0, 600.0, Vector3::new(1.0, 1.0, 1.0)));
+                lights.push(PointLight::new(700.0, 50.0, 600.0, Vector3::new(1.0, 1.0, 1.0)));
 
                 let mut cam = EquilinearCamera {
                     width: 5,
@@ -66,6 +74,9 @@ fn ducky_primitive_storage(c: &mut Criterion) {
                     yaw: 0.0,   //rotation counterclockwise-clockwise
                     pos: Vector3::new(100.0, 50.0, 200.0),
                     vertical_viewangle: 40.0,
+                    recursion_depth: 10.0,
+                    sampler: RegularSampler::new(1),
+                    filter: BoxFilter::new(),
                 };
                 let w = World::new(Box::new(PrimitiveStorage { elements: elements.elements }), lights);
                 let _image = cam.render(&w, false);
@@ -83,7 +94,7 @@ fn ducky_bv_storage(c: &mut Criterion) {
                 let mut elements = file_parser.elements;
                 
                 let mut lights = Vec::new();
-                lights.push(Light::new(700.0, 50.0, 600.0, Vector3::new(1.0, 1.0, 1.0)));
+                lights.push(PointLight::new(700.0, 50.0, 600.0, Vector3::new(1.0, 1.0, 1.0)));
 
                 let mut cam = EquilinearCamera {
                     width: 5,
@@ -93,14 +104,131 @@ fn ducky_bv_storage(c: &mut Criterion) {
                     yaw: 0.0,   //rotation counterclockwise-clockwise
                     pos: Vector3::new(100.0, 50.0, 200.0),
                     vertical_viewangle: 40.0,
+                    recursion_depth: 10.0,
+                    sampler: RegularSampler::new(1),
+                    filter: BoxFilter::new(),
                 };
-                let w = World::new(elements.into_storage(), lights);
+                // kept on the midpoint builder, so results compare with earlier runs
+                let w = World::new(elements.into_storage_with(&Builder::Midpoint), lights);
                 let _image = cam.render(&w, false);
             }
         )
     );
 }
 
-criterion_group!(benches, parsing_ducky, ducky_bv_storage, ducky_primitive_storage);
+// Only the rendering is measured, parsing and building the storage happen once
+fn ducky_traversal(c: &mut Criterion, name: &str, builder: Builder) {
+    let mut file_parser = FileParser::new();
+    file_parser.parse("ducky.obj".to_string()).expect("file ducky.obj to benchmark");
+
+    let mut lights = Vec::new();
+    lights.push(PointLight::new(700.0, 50.0, 600.0, Vector3::new(1.0, 1.0, 1.0)));
+
+    let cam = EquilinearCamera {
+        width: 40,
+        height: 40,
+        roll: 0.0, // down-up
+        pitch: 3.7, //right-left
+        yaw: 0.0,   //rotation counterclockwise-clockwise
+        pos: Vector3::new(100.0, 50.0, 200.0),
+        vertical_viewangle: 40.0,
+        recursion_depth: 10.0,
+        sampler: RegularSampler::new(1),
+        filter: BoxFilter::new(),
+    };
+    let w = World::new(file_parser.elements.into_storage_with(&builder), lights);
+    c.bench_function(name, move |b| b.iter(|| cam.render(&w, false)));
+}
+
+fn ducky_midpoint_traversal(c: &mut Criterion) {
+    ducky_traversal(c, "ducky_midpoint_traversal", Builder::Midpoint);
+}
+
+fn ducky_sah_traversal(c: &mut Criterion) {
+    ducky_traversal(c, "ducky_sah_traversal", Builder::Sah(SahParameters::default()));
+}
+
+// 4x4 duckies next to each other
+fn ducky_grid() -> Vec<Matrix4<f64>> {
+    let mut transforms = Vec::new();
+    for x in 0..4 {
+        for z in 0..4 {
+            let translation = Vector3::new(x as f64 * -40.0, 0.0, z as f64 * -40.0);
+            transforms.push(Instance::transform_from(translation, 0.0, 0.0, 0.0, Vector3::new(1.0, 1.0, 1.0)));
+        }
+    }
+    transforms
+}
+
+// Instances of one parsed ducky, in a top level hierarchy over the per mesh hierarchies
+fn ducky_instances() -> Collector {
+    let mut file_parser = FileParser::new();
+    file_parser.parse("ducky.obj".to_string()).expect("file ducky.obj to benchmark");
+    let count = file_parser.elements.get_primitive_count();
+    let ducky: Arc<Bounded> = Arc::new(file_parser.elements.into_bounded_storage());
+
+    let mut elements = Collector::new();
+    for transform in ducky_grid() {
+        elements.add_instance(Instance::new(ducky.clone(), transform).expect("invertible transform"), count);
+    }
+    elements
+}
+
+// Every ducky parsed on its own with the transform applied to its vertices
+fn ducky_flattened() -> Collector {
+    let mut file_parser = FileParser::new();
+    for transform in ducky_grid() {
+        file_parser.set_transform(transform).expect("invertible transform");
+        file_parser.parse("ducky.obj".to_string()).expect("file ducky.obj to benchmark");
+    }
+    file_parser.elements
+}
+
+fn ducky_grid_traversal(c: &mut Criterion, name: &str, elements: Collector) {
+    let mut lights = Vec::new();
+    lights.push(PointLight::new(700.0, 50.0, 600.0, Vector3::new(1.0, 1.0, 1.0)));
+
+    let cam = EquilinearCamera {
+        width: 40,
+        height: 40,
+        roll: 0.0, // down-up
+        pitch: 3.7, //right-left
+        yaw: 0.0,   //rotation counterclockwise-clockwise
+        pos: Vector3::new(100.0, 50.0, 200.0),
+        vertical_viewangle: 40.0,
+        recursion_depth: 10.0,
+        sampler: RegularSampler::new(1),
+        filter: BoxFilter::new(),
+    };
+    let w = World::new(elements.into_storage(), lights);
+    c.bench_function(name, move |b| b.iter(|| cam.render(&w, false)));
+}
+
+fn ducky_instanced_traversal(c: &mut Criterion) {
+    ducky_grid_traversal(c, "ducky_instanced_traversal", ducky_instances());
+}
+
+fn ducky_flattened_traversal(c: &mut Criterion) {
+    ducky_grid_traversal(c, "ducky_flattened_traversal", ducky_flattened());
+}
+
+// Moving one instance only rebuilds the top level
+fn ducky_instanced_move(c: &mut Criterion) {
+    let (_elements, mut instances) = ducky_instances().into_storages(&Builder::default());
+    let moved = Matrix4::new_translation(&Vector3::new(0.0, 10.0, 0.0));
+    c.bench_function("ducky_instanced_move",
+        move |b| b.iter(|| instances.set_transform(0, moved).expect("invertible transform"))
+    );
+}
+
+// Without instances, moving a ducky means parsing and building everything again
+fn ducky_flattened_move(c: &mut Criterion) {
+    c.bench_function("ducky_flattened_move",
+        |b| b.iter(|| ducky_flattened().into_storage())
+    );
+}
+
+criterion_group!(benches, parsing_ducky, ducky_bv_storage, ducky_primitive_storage, ducky_midpoint_traversal, ducky_sah_traversal,
+    ducky_instanced_traversal, ducky_flattened_traversal, ducky_instanced_move, ducky_flattened_move);
 criterion_main!(benches);
 
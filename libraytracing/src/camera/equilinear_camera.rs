@@ -14,14 +14,14 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
+use crate::camera::filter::Filter;
+use crate::camera::sampler::Sampler;
 use crate::camera::*;
 use crate::helpers::*;
 use crate::ray::Ray;
 use crate::world::World;
-use image::{DynamicImage, GenericImage};
 use na::{Rotation3, Unit, Vector3};
 use std::f64;
-use indicatif::ProgressBar;
 
 pub struct EquilinearCamera {
     pub height: u32,
@@ -31,6 +31,9 @@ pub struct EquilinearCamera {
     pub yaw: f64,
     pub pos: Vector3<f64>,
     pub vertical_viewangle: f64,
+    pub recursion_depth: f64,
+    pub sampler: Box<Sampler>,
+    pub filter: Box<Filter>,
 }
 
 impl EquilinearCamera {
@@ -43,43 +46,37 @@ impl EquilinearCamera {
 }
 
 impl Camera for EquilinearCamera {
-    fn render(&self, world: &World, progress: bool) -> DynamicImage {
+    fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    fn ray_at(&self, x: f64, y: f64) -> Ray {
         // algorithm for direction taken from https://www.scratchapixel.com/code.php?id=3&origin=/lessons/3d-basic-rendering/introduction-to-ray-tracing
-        let mut img = DynamicImage::new_rgb8(self.width, self.height);
         let inv_width = 1.0 / self.width as f64;
         let inv_height = 1.0 / self.height as f64;
         let aspectratio = self.width as f64 / self.height as f64;
         let vertical_half_canvas_size =
             (f64::consts::FRAC_PI_2 * self.vertical_viewangle / 180.0).tan();
         let rot_matrix = Rotation3::from_euler_angles(self.roll, self.pitch, self.yaw);
-        let bar = if progress {
-            Some(ProgressBar::new((self.width * self.height).into()))
-        } else {
-            None
-        };
-        for x in 0..self.width {
-            for y in 0..self.height {
-                let xx = (2.0 * ((x as f64 + 0.5) * inv_width) - 1.0)
-                    * vertical_half_canvas_size
-                    * aspectratio;
-                let yy = (2.0 * ((y as f64 + 0.5) * inv_height) - 1.) * vertical_half_canvas_size;
-                let dir = rot_matrix * Vector3::new(xx, yy, 1.0).normalize();
-                let ray = Ray {
-                    dir: Unit::new_normalize(dir),
-                    start: self.pos,
-                };
-                let rgb = world.color(ray, 10.0);
-
-                img.put_pixel(x, self.height - y - 1, rgb);
-                if let Some(bar) = &bar {
-                    bar.inc(1);
-                }
+        let xx = (2.0 * (x * inv_width) - 1.0) * vertical_half_canvas_size * aspectratio;
+        let yy = (2.0 * (y * inv_height) - 1.) * vertical_half_canvas_size;
+        let dir = rot_matrix * Vector3::new(xx, yy, 1.0).normalize();
+        Ray::new(self.pos, Unit::new_normalize(dir))
+    }
 
-            }
-        }
-        if let Some(bar) = bar {
-            bar.finish();
-        }
-        img
+    fn appearance_at(&self, world: &World, x: u32, y: u32) -> Vector3<f64> {
+        supersample(
+            world,
+            &*self.sampler,
+            &*self.filter,
+            self.recursion_depth,
+            x,
+            y,
+            |x, y| self.ray_at(x, y),
+        )
     }
 }
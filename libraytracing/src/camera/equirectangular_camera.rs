@@ -14,11 +14,12 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
+use crate::camera::filter::Filter;
+use crate::camera::sampler::Sampler;
 use crate::camera::*;
 use crate::helpers::polar2vector;
 use crate::ray::Ray;
 use crate::world::World;
-use image::{DynamicImage, GenericImage};
 use na::{Rotation3, Unit, Vector3};
 use std::f64;
 
@@ -28,29 +29,39 @@ pub struct EquirectangularCamera {
     pub pitch: f64,
     pub yaw: f64,
     pub pos: Vector3<f64>,
+    pub recursion_depth: f64,
+    pub sampler: Box<Sampler>,
+    pub filter: Box<Filter>,
 }
 
 impl Camera for EquirectangularCamera {
-    fn render(&self, world: &World, progress: bool) -> DynamicImage {
-        let width = self.height * 2;
-        let mut img = DynamicImage::new_rgb8(width, self.height);
+    fn get_width(&self) -> u32 {
+        self.height * 2
+    }
+
+    fn get_height(&self) -> u32 {
+        self.height
+    }
 
+    fn ray_at(&self, x: f64, y: f64) -> Ray {
+        let width = self.get_width();
         let rot_matrix = Rotation3::from_euler_angles(self.roll, self.pitch, self.yaw);
-        for x in 0..width {
-            for y in 0..self.height {
-                let gamma = (y as f64) / (self.height as f64) * f64::consts::PI;
-                let phi = (2.0 * (x as f64) / (width as f64) - 1.0) * f64::consts::PI;
+        let gamma = y / (self.height as f64) * f64::consts::PI;
+        let phi = (2.0 * x / (width as f64) - 1.0) * f64::consts::PI;
 
-                let dir = rot_matrix * polar2vector(gamma, phi).normalize();
-                let ray = Ray {
-                    dir: Unit::new_normalize(dir),
-                    start: self.pos,
-                };
-                let rgb = world.color(ray, 10.0);
+        let dir = rot_matrix * polar2vector(gamma, phi).normalize();
+        Ray::new(self.pos, Unit::new_normalize(dir))
+    }
 
-                img.put_pixel(x, self.height - y - 1, rgb);
-            }
-        }
-        img
+    fn appearance_at(&self, world: &World, x: u32, y: u32) -> Vector3<f64> {
+        supersample(
+            world,
+            &*self.sampler,
+            &*self.filter,
+            self.recursion_depth,
+            x,
+            y,
+            |x, y| self.ray_at(x, y),
+        )
     }
 }
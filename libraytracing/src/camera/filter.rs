@@ -0,0 +1,142 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+/// Reconstruction filter that weights the samples of a pixel
+pub trait Filter: Send + Sync {
+    /// Half of the width of the filter in pixels, samples are spread over
+    /// [-radius, radius] x [-radius, radius] around the pixel center
+    fn get_radius(&self) -> f64;
+    /// Weight of a sample at the offset (x, y) from the pixel center
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+/// All samples count the same
+pub struct BoxFilter {
+    pub radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new() -> Box<Filter> {
+        Box::new(BoxFilter { radius: 0.5 })
+    }
+}
+
+impl Filter for BoxFilter {
+    fn get_radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, _x: f64, _y: f64) -> f64 {
+        1.0
+    }
+}
+
+/// Weight falls linearly to zero at radius
+pub struct TentFilter {
+    pub radius: f64,
+}
+
+impl TentFilter {
+    pub fn new() -> Box<Filter> {
+        Box::new(TentFilter { radius: 1.0 })
+    }
+}
+
+impl Filter for TentFilter {
+    fn get_radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        (self.radius - x.abs()).max(0.0) * (self.radius - y.abs()).max(0.0)
+    }
+}
+
+/// Gaussian bell exp(-alpha * d²), shifted down so that it reaches zero at radius
+pub struct GaussianFilter {
+    pub radius: f64,
+    pub alpha: f64,
+}
+
+impl GaussianFilter {
+    pub fn new() -> Box<Filter> {
+        Box::new(GaussianFilter {
+            radius: 1.5,
+            alpha: 2.0,
+        })
+    }
+
+    fn gaussian(&self, d: f64) -> f64 {
+        ((-self.alpha * d * d).exp() - (-self.alpha * self.radius * self.radius).exp()).max(0.0)
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn get_radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.gaussian(x) * self.gaussian(y)
+    }
+}
+
+/// Mitchell-Netravali cubic filter, b = c = 1/3 is their recommendation
+pub struct MitchellFilter {
+    pub radius: f64,
+    pub b: f64,
+    pub c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new() -> Box<Filter> {
+        Box::new(MitchellFilter {
+            radius: 2.0,
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        })
+    }
+
+    fn mitchell(&self, d: f64) -> f64 {
+        // the cubic is defined on [-2, 2]
+        let x = (2.0 * d / self.radius).abs();
+        let (b, c) = (self.b, self.c);
+        if x < 1.0 {
+            ((12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                + (6.0 - 2.0 * b))
+                / 6.0
+        } else if x < 2.0 {
+            ((-b - 6.0 * c) * x.powi(3)
+                + (6.0 * b + 30.0 * c) * x.powi(2)
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c))
+                / 6.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn get_radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.mitchell(x) * self.mitchell(y)
+    }
+}
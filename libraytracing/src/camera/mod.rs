@@ -16,9 +16,263 @@
 
 pub mod equilinear_camera;
 pub mod equirectangular_camera;
+pub mod filter;
+pub mod sampler;
+use crate::camera::filter::Filter;
+use crate::camera::sampler::Sampler;
+use crate::error::Error;
+use crate::framebuffer::{FrameBuffer, Layers};
+use crate::pass::Pass;
+use crate::ray::Ray;
 use crate::world::World;
-use image::DynamicImage;
+use na::Vector3;
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
 
-pub trait Camera {
-    fn render(&self, world: &World, progess: bool) -> DynamicImage;
+/// Edge length in pixels of the square tiles handed to the worker threads
+pub const TILE_SIZE: u32 = 32;
+
+pub trait Camera: Sync {
+    fn get_width(&self) -> u32;
+    fn get_height(&self) -> u32;
+
+    /// Ray through the point (x, y) of the image plane, in pixels from the bottom left corner
+    fn ray_at(&self, x: f64, y: f64) -> Ray;
+
+    /// Linear color of the pixel (x, y), with y counted from the bottom of the image
+    fn appearance_at(&self, world: &World, x: u32, y: u32) -> Vector3<f64>;
+
+    /// Render the image pixel by pixel on the current thread
+    fn render(&self, world: &World, progress: bool) -> FrameBuffer {
+        let width = self.get_width();
+        let height = self.get_height();
+        let mut img = FrameBuffer::new(width, height);
+        let bar = if progress {
+            Some(ProgressBar::new((width * height).into()))
+        } else {
+            None
+        };
+        for x in 0..width {
+            for y in 0..height {
+                let appearance = self.appearance_at(world, x, y);
+                img.put_pixel(x, height - y - 1, appearance.map(|c| c as f32));
+                if let Some(bar) = &bar {
+                    bar.inc(1);
+                }
+            }
+        }
+        if let Some(bar) = bar {
+            bar.finish();
+        }
+        img
+    }
+
+    /// Render the image in tiles of TILE_SIZE x TILE_SIZE pixels spread over a pool of
+    /// `threads` threads (0 uses one thread per logical core). Gives the same image as `render`.
+    fn render_parallel(
+        &self,
+        world: &World,
+        progress: bool,
+        threads: usize,
+    ) -> Result<FrameBuffer, Error> {
+        Ok(self.render_layers(world, progress, threads, &[])?.beauty)
+    }
+
+    /// Like render_parallel, but renders the given passes next to the image
+    fn render_layers(
+        &self,
+        world: &World,
+        progress: bool,
+        threads: usize,
+        passes: &[Pass],
+    ) -> Result<Layers, Error> {
+        let width = self.get_width();
+        let height = self.get_height();
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|error| Error::from(format!("Could not create the thread pool: {}", error)))?;
+        let bar = if progress {
+            Some(ProgressBar::new((width * height).into()))
+        } else {
+            None
+        };
+
+        let tiles = get_tiles(width, height);
+        // for every pixel of a tile: the appearance followed by the values of the passes
+        let rendered: Vec<(Tile, Vec<Vec<Vector3<f32>>>)> = pool.install(|| {
+            tiles
+                .into_par_iter()
+                .map(|tile| {
+                    let mut pixels = Vec::with_capacity((tile.width * tile.height) as usize);
+                    for y in tile.y..tile.y + tile.height {
+                        for x in tile.x..tile.x + tile.width {
+                            let mut values = Vec::with_capacity(passes.len() + 1);
+                            let appearance = self.appearance_at(world, x, y);
+                            values.push(appearance.map(|c| c as f32));
+                            if !passes.is_empty() {
+                                let ray = self.ray_at(x as f64 + 0.5, y as f64 + 0.5);
+                                for pass in passes {
+                                    values.push(pass.evaluate(world, &ray));
+                                }
+                            }
+                            pixels.push(values);
+                        }
+                    }
+                    if let Some(bar) = &bar {
+                        bar.inc((tile.width * tile.height).into());
+                    }
+                    (tile, pixels)
+                })
+                .collect()
+        });
+
+        let mut beauty = FrameBuffer::new(width, height);
+        let mut pass_buffers: Vec<FrameBuffer> = passes
+            .iter()
+            .map(|_| FrameBuffer::new(width, height))
+            .collect();
+        for (tile, pixels) in rendered {
+            for (i, values) in pixels.into_iter().enumerate() {
+                let x = tile.x + i as u32 % tile.width;
+                let y = height - (tile.y + i as u32 / tile.width) - 1;
+                beauty.put_pixel(x, y, values[0]);
+                for (buffer, value) in pass_buffers.iter_mut().zip(&values[1..]) {
+                    buffer.put_pixel(x, y, *value);
+                }
+            }
+        }
+        if let Some(bar) = bar {
+            bar.finish();
+        }
+        Ok(Layers {
+            beauty,
+            passes: passes.iter().cloned().zip(pass_buffers).collect(),
+        })
+    }
+}
+
+/// Rectangular part of the image, the last row and column of tiles may be smaller than TILE_SIZE
+#[derive(Debug, Clone, Copy)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub fn get_tiles(width: u32, height: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(TILE_SIZE as usize) {
+        for x in (0..width).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+/// Linear color of the pixel (x, y): the appearances seen by the rays through the sample positions
+/// of `sampler`, spread over the support of `filter` and weighted by it.
+/// `ray_at` gives the ray through a position on the image plane, in pixels.
+pub fn supersample<F: Fn(f64, f64) -> Ray>(
+    world: &World,
+    sampler: &Sampler,
+    filter: &Filter,
+    recursion_depth: f64,
+    x: u32,
+    y: u32,
+    ray_at: F,
+) -> Vector3<f64> {
+    let radius = filter.get_radius();
+    let mut appearance = Vector3::new(0.0, 0.0, 0.0);
+    let mut total_weight = 0.0;
+    for sample in sampler.get_samples(x, y) {
+        let dx = (sample.x - 0.5) * 2.0 * radius;
+        let dy = (sample.y - 0.5) * 2.0 * radius;
+        let weight = filter.evaluate(dx, dy);
+        if weight == 0.0 {
+            continue;
+        }
+        let ray = ray_at(x as f64 + 0.5 + dx, y as f64 + 0.5 + dy);
+        appearance += weight * world.appearance(ray, recursion_depth);
+        total_weight += weight;
+    }
+    if total_weight != 0.0 {
+        appearance /= total_weight;
+    }
+    appearance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::equilinear_camera::EquilinearCamera;
+    use crate::camera::filter::TentFilter;
+    use crate::camera::sampler::JitteredSampler;
+    use crate::shader::diffuse_shader::DiffuseShader;
+    use crate::shader::mirror_shader::MirrorShader;
+    use crate::shader::Shader;
+    use crate::storage::primitive_storage::PrimitiveStorage;
+    use crate::world::area_light::SphereLight;
+    use crate::world::light::PointLight;
+    use crate::world::sphere::Sphere;
+    use crate::world::Interceptable;
+
+    fn sphere(center: Vector3<f64>, radius: f64, shader: Box<Shader>) -> Sphere {
+        Sphere {
+            center,
+            radius,
+            shader,
+            roll: 0.0,
+            pitch: 0.0,
+            yaw: 0.0,
+        }
+    }
+
+    #[test]
+    fn parallel_render_matches_serial_render() {
+        let orange = DiffuseShader::new(Vector3::new(0.8, 0.4, 0.2));
+        let gray = DiffuseShader::new(Vector3::repeat(0.5));
+        let elements: Vec<Box<Interceptable>> = vec![
+            Box::new(sphere(Vector3::zeros(), 1.0, orange)),
+            Box::new(sphere(Vector3::new(2.0, 0.5, 1.0), 0.8, MirrorShader::new())),
+            Box::new(sphere(Vector3::new(0.0, 101.0, 0.0), 100.0, gray)),
+        ];
+        let lights = vec![
+            PointLight::new(-3.0, -5.0, -4.0, Vector3::new(1.0, 1.0, 1.0)),
+            Box::new(SphereLight {
+                center: Vector3::new(3.0, -4.0, -2.0),
+                radius: 0.5,
+                color: Vector3::repeat(1.0),
+                intensity: 20.0,
+                samples: 4,
+                visible: true,
+            }),
+        ];
+        let world = World::new(Box::new(PrimitiveStorage { elements }), lights);
+        // not a multiple of TILE_SIZE, so there are partial tiles
+        let mut camera = EquilinearCamera {
+            width: 40,
+            height: 35,
+            roll: 0.0,
+            pitch: 0.0,
+            yaw: 0.0,
+            pos: Vector3::new(0.0, -1.0, -6.0),
+            vertical_viewangle: 40.0,
+            recursion_depth: 4.0,
+            sampler: JitteredSampler::new(4),
+            filter: TentFilter::new(),
+        };
+        camera.point_at(Vector3::zeros());
+
+        let serial = camera.render(&world, false);
+        let parallel = camera.render_parallel(&world, false, 4).unwrap();
+        assert_eq!(serial.get_pixels(), parallel.get_pixels());
+    }
 }
@@ -0,0 +1,195 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use na::Vector2;
+
+/// Chooses where the rays of a pixel go through
+pub trait Sampler: Send + Sync {
+    /// Sample positions for the pixel (x, y), as offsets in [0, 1) x [0, 1)
+    fn get_samples(&self, x: u32, y: u32) -> Vec<Vector2<f64>>;
+}
+
+/// Regular grid of samples_per_axis x samples_per_axis samples,
+/// one sample is the center of the pixel
+pub struct RegularSampler {
+    pub samples_per_axis: u32,
+}
+
+impl RegularSampler {
+    /// Sampler with at least `samples` samples (rounded up to a square number)
+    pub fn new(samples: u32) -> Box<Sampler> {
+        Box::new(RegularSampler {
+            samples_per_axis: samples_per_axis(samples),
+        })
+    }
+}
+
+impl Sampler for RegularSampler {
+    fn get_samples(&self, _x: u32, _y: u32) -> Vec<Vector2<f64>> {
+        let n = self.samples_per_axis as f64;
+        let mut samples = Vec::new();
+        for i in 0..self.samples_per_axis {
+            for j in 0..self.samples_per_axis {
+                samples.push(Vector2::new((i as f64 + 0.5) / n, (j as f64 + 0.5) / n));
+            }
+        }
+        samples
+    }
+}
+
+/// Stratified sampling: one random sample in every cell of a
+/// samples_per_axis x samples_per_axis grid
+pub struct JitteredSampler {
+    pub samples_per_axis: u32,
+}
+
+impl JitteredSampler {
+    /// Sampler with at least `samples` samples (rounded up to a square number)
+    pub fn new(samples: u32) -> Box<Sampler> {
+        Box::new(JitteredSampler {
+            samples_per_axis: samples_per_axis(samples),
+        })
+    }
+}
+
+impl Sampler for JitteredSampler {
+    fn get_samples(&self, x: u32, y: u32) -> Vec<Vector2<f64>> {
+        let n = self.samples_per_axis as f64;
+        let mut samples = Vec::new();
+        for i in 0..self.samples_per_axis {
+            for j in 0..self.samples_per_axis {
+                let cell = i * self.samples_per_axis + j;
+                samples.push(Vector2::new(
+                    (i as f64 + random(x, y, 2 * cell)) / n,
+                    (j as f64 + random(x, y, 2 * cell + 1)) / n,
+                ));
+            }
+        }
+        samples
+    }
+}
+
+/// Low-discrepancy samples from the Halton sequence in base 2 and 3,
+/// shifted randomly for every pixel
+pub struct HaltonSampler {
+    pub samples: u32,
+}
+
+impl HaltonSampler {
+    pub fn new(samples: u32) -> Box<Sampler> {
+        Box::new(HaltonSampler {
+            samples: samples.max(1),
+        })
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn get_samples(&self, x: u32, y: u32) -> Vec<Vector2<f64>> {
+        (0..self.samples).map(|i| shifted_halton(x, y, i)).collect()
+    }
+}
+
+/// The i-th sample of the HaltonSampler for the pixel (x, y), any number of samples is
+/// spread evenly without rounding it to a square
+pub fn shifted_halton(x: u32, y: u32, i: u32) -> Vector2<f64> {
+    let shift = Vector2::new(random(x, y, 0), random(x, y, 1));
+    let sample = Vector2::new(radical_inverse(2, i + 1), radical_inverse(3, i + 1));
+    (sample + shift).map(|v| v.fract())
+}
+
+/// Low-discrepancy samples from the first two dimensions of the Sobol sequence,
+/// scrambled randomly for every pixel
+pub struct SobolSampler {
+    pub samples: u32,
+}
+
+impl SobolSampler {
+    pub fn new(samples: u32) -> Box<Sampler> {
+        Box::new(SobolSampler {
+            samples: samples.max(1),
+        })
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn get_samples(&self, x: u32, y: u32) -> Vec<Vector2<f64>> {
+        let scramble_x = hash(x, y, 0);
+        let scramble_y = hash(x, y, 1);
+        (0..self.samples)
+            .map(|i| {
+                Vector2::new(
+                    to_unit(i.reverse_bits() ^ scramble_x),
+                    to_unit(sobol(i) ^ scramble_y),
+                )
+            })
+            .collect()
+    }
+}
+
+fn samples_per_axis(samples: u32) -> u32 {
+    ((samples as f64).sqrt().ceil() as u32).max(1)
+}
+
+/// Second dimension of the Sobol sequence
+fn sobol(mut i: u32) -> u32 {
+    let mut result = 0;
+    let mut v: u32 = 1 << 31;
+    while i != 0 {
+        if i & 1 != 0 {
+            result ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+/// Mirror the digits of i in the given base at the decimal point
+fn radical_inverse(base: u32, mut i: u32) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut factor = inv_base;
+    let mut result = 0.0;
+    while i > 0 {
+        result += (i % base) as f64 * factor;
+        i /= base;
+        factor *= inv_base;
+    }
+    result
+}
+
+fn to_unit(v: u32) -> f64 {
+    v as f64 / 4_294_967_296.0
+}
+
+/// Pseudo random number in [0, 1), always the same for the same pixel and index
+/// so that the image doesn't depend on the order the pixels are rendered in
+fn random(x: u32, y: u32, i: u32) -> f64 {
+    to_unit(hash(x, y, i))
+}
+
+fn hash(x: u32, y: u32, i: u32) -> u32 {
+    let mut h = x
+        .wrapping_mul(0x8da6_b343)
+        ^ y.wrapping_mul(0xd816_3841)
+        ^ i.wrapping_mul(0xcb1a_b31f);
+    // finalizer of murmur3
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h
+}
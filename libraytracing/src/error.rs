@@ -24,6 +24,21 @@ pub enum Error {
     ParseError(wavefront_obj::ParseError),
     Io(io::Error),
     Time(std::time::SystemTimeError),
+    Toml(toml::de::Error),
+    /// Invalid entry in a scene description file, e.g. `sphere[2]`
+    Scene { entry: String, message: String },
+    /// Invalid line in a wavefront obj or mtl file, lines start at 1
+    File {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    /// usemtl names a material that none of the mtl files defines
+    UnknownMaterial {
+        path: String,
+        line: usize,
+        name: String,
+    },
     Error(String),
 }
 
@@ -34,6 +49,21 @@ impl Display for Error {
             Error::ParseError(ref error) => error.message.fmt(formatter),
             Error::Error(ref error) => error.fmt(formatter),
             Error::Time(ref error) => error.fmt(formatter),
+            Error::Toml(ref error) => error.fmt(formatter),
+            Error::Scene {
+                ref entry,
+                ref message,
+            } => write!(formatter, "{}: {}", entry, message),
+            Error::File {
+                ref path,
+                line,
+                ref message,
+            } => write!(formatter, "{}:{}: {}", path, line, message),
+            Error::UnknownMaterial {
+                ref path,
+                line,
+                ref name,
+            } => write!(formatter, "{}:{}: Material {} doesn't exist", path, line, name),
         }
     }
 }
@@ -50,6 +80,12 @@ impl From<std::time::SystemTimeError> for Error {
     }
 }
 
+impl From<toml::de::Error> for Error {
+    fn from(error: toml::de::Error) -> Self {
+        Error::Toml(error)
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(error: io::Error) -> Self {
         Error::Io(error)
@@ -0,0 +1,69 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::pass::Pass;
+use crate::tone_mapping::{linear_to_srgb, ToneMapper};
+use image::{DynamicImage, ImageBuffer, Rgb};
+use na::Vector3;
+
+/// The rendered image together with the requested passes
+pub struct Layers {
+    pub beauty: FrameBuffer,
+    pub passes: Vec<(Pass, FrameBuffer)>,
+}
+
+/// Linear RGB image with float precision, the top row comes first
+pub struct FrameBuffer {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<Vector3<f32>>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32) -> Self {
+        FrameBuffer {
+            width,
+            height,
+            pixels: vec![Vector3::new(0.0, 0.0, 0.0); (width * height) as usize],
+        }
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Vector3<f32> {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn put_pixel(&mut self, x: u32, y: u32, color: Vector3<f32>) {
+        self.pixels[(y * self.width + x) as usize] = color;
+    }
+
+    /// All pixels row by row
+    pub fn get_pixels(&self) -> &[Vector3<f32>] {
+        &self.pixels
+    }
+
+    /// Scale by 2^exposure, compress the range with tone_mapper and encode in sRGB
+    pub fn to_image(&self, exposure: f64, tone_mapper: &ToneMapper) -> DynamicImage {
+        let scale = 2f64.powf(exposure);
+        let buffer = ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            let color = self.get_pixel(x, y).map(|c| c as f64 * scale);
+            let srgb = tone_mapper
+                .map(color)
+                .map(|c| (linear_to_srgb(c.min(1.0).max(0.0)) * 255.0).round() as u8);
+            Rgb([srgb.x, srgb.y, srgb.z])
+        });
+        DynamicImage::ImageRgb8(buffer)
+    }
+}
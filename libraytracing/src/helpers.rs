@@ -50,6 +50,17 @@ pub fn vector2polar<T: na::Real>(v: &Vector3<T>) -> (T, T) {
     )
 }
 
+/// Two unit vectors that are perpendicular to each other and to the unit vector n
+pub fn orthonormal_basis(n: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    let helper = if n.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    let u = n.cross(&helper).normalize();
+    (u, n.cross(&u))
+}
+
 pub fn bounded2interceptable(bounded: Vec<Box<Bounded>>) -> Vec<Box<Interceptable>> {
     let mut result = Vec::new();
     for e in bounded {
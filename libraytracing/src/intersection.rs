@@ -15,15 +15,25 @@
  * file for more details. **/
 
 use crate::shader::Shader;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use image::Rgba;
 use na::{Vector2, Vector3};
 
 pub struct Intersection<'a> {
     pub pos: Vector3<f64>,
+    /// Geometric normal, pointing to the outside of the surface no matter which side the
+    /// ray came from. Triangles and planes are seen from the outside if their corners are
+    /// counterclockwise, unless they have vertex normals that say otherwise.
     pub normal_at_surface: Vector3<f64>,
+    /// Normal the shaders light the surface with, e.g. interpolated from vertex normals.
+    /// On the same side as normal_at_surface.
+    pub shading_normal: Vector3<f64>,
     pub pos_on_surface: Vector2<f64>,
     pub shader: &'a Box<Shader>,
+    /// Ids given by the Collector, 0 if the primitive wasn't collected.
+    /// Meshes set the number of the face starting at 1, the Collector turns it into an id.
+    pub primitive_id: usize,
+    pub object_id: usize,
 }
 
 impl<'a> Intersection<'a> {
@@ -33,11 +43,13 @@ impl<'a> Intersection<'a> {
         world: &World,
         recursion_depth: f64,
     ) -> Rgba<u8> {
+        let lights = VisibleLights::new(world, self.pos);
         self.shader.get_color_for(
             self.pos,
             ray_dir,
-            self.normal_at_surface,
+            self.shading_normal,
             world,
+            &lights,
             self.pos_on_surface,
             recursion_depth,
         )
@@ -48,11 +60,13 @@ impl<'a> Intersection<'a> {
         world: &World,
         recursion_depth: f64,
     ) -> Vector3<f64> {
+        let lights = VisibleLights::new(world, self.pos);
         self.shader.get_appearance_for(
             self.pos,
             ray_dir,
-            self.normal_at_surface,
+            self.shading_normal,
             world,
+            &lights,
             self.pos_on_surface,
             recursion_depth,
         )
@@ -20,10 +20,15 @@ extern crate wavefront_obj;
 
 pub mod camera;
 pub mod error;
+pub mod framebuffer;
 pub mod helpers;
 pub mod intersection;
 pub mod obj;
+pub mod output;
+pub mod pass;
 pub mod ray;
+pub mod scene;
 pub mod shader;
 pub mod storage;
+pub mod tone_mapping;
 pub mod world;
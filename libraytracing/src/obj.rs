@@ -16,26 +16,47 @@
 
 use crate::error::Error;
 use crate::shader::{
-    diffuse_shader::DiffuseShader, get_phong, mirror_shader::MirrorShader, ambient_shader::AmbientShader,
-    monochrome_shader::MonochromeShader, specular_shader::SpecularShader, Shader,
+    dielectric_shader::{DielectricShader, Fresnel}, diffuse_shader::DiffuseShader,
+    emissive_shader::EmissiveShader, get_phong, mirror_shader::MirrorShader,
+    ambient_shader::AmbientShader,
+    monochrome_shader::MonochromeShader, specular_shader::SpecularShader,
+    texture_shader::{Texture, TextureFilter, TextureShader, WrapMode}, Shader,
 };
 use crate::storage::collector::Collector;
-use crate::world::triangle::Triangle;
-use na::Vector3;
+use crate::world::area_light::MeshLight;
+use crate::world::light::Light;
+use crate::world::triangle_mesh::{Face, TriangleMesh};
+use na::{Matrix3, Matrix4, Point3, Vector2, Vector3, U3};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
 use wavefront_obj::mtl::Material;
-use wavefront_obj::mtl::{parse as mtl_parse, Color, Illumination};
-use wavefront_obj::obj::parse as obj_parse;
-use wavefront_obj::obj::Primitive;
+use wavefront_obj::mtl::{parse as mtl_parse, Color};
 
 /// FileParser struct
 /// Can parse obj and mtl wavefront files
 /// After parsing multiple files with this struct, get the elements field
 pub struct FileParser {
     pub elements: Collector,
+    /// Things in the files that couldn't be rendered as they are described
+    pub warnings: Vec<String>,
+    /// Emissive faces as light sources, if enabled with set_mesh_lights
+    pub lights: Vec<Box<Light>>,
+    /// Color of the phong shader for faces without a material, and for the ones whose
+    /// material doesn't exist if set
+    default_material: Option<Vector3<f64>>,
+    /// Shadow rays per shading point of the mesh lights, None if emissive faces only glow
+    mesh_light_samples: Option<u32>,
     materials: HashMap<String, Material>,
+    /// Diffuse maps of the materials by material name
+    textures: HashMap<String, Arc<Texture>>,
+    /// Illumination models by material name, the mtl parser only reads 0 to 3
+    illumination_models: HashMap<String, usize>,
+    /// Applied to the vertices of obj files
+    transform: Matrix4<f64>,
+    normal_matrix: Matrix3<f64>,
 }
 
 impl FileParser {
@@ -43,10 +64,42 @@ impl FileParser {
     pub fn new() -> Self {
         FileParser {
             elements: Collector::new(),
+            warnings: Vec::new(),
+            lights: Vec::new(),
+            default_material: None,
+            mesh_light_samples: None,
             materials: HashMap::new(),
+            textures: HashMap::new(),
+            illumination_models: HashMap::new(),
+            transform: Matrix4::identity(),
+            normal_matrix: Matrix3::identity(),
         }
     }
 
+    /// Transform the geometry of the obj files parsed after this, e.g. to bake instances
+    /// into one flat storage
+    pub fn set_transform(&mut self, transform: Matrix4<f64>) -> Result<(), Error> {
+        let inverse = transform
+            .try_inverse()
+            .ok_or_else(|| Error::from("The transform of an obj file must be invertible"))?;
+        self.transform = transform;
+        self.normal_matrix = inverse.fixed_slice::<U3, U3>(0, 0).transpose();
+        Ok(())
+    }
+
+    /// Faces whose material doesn't exist get a phong shader of the color instead of causing
+    /// an Error::UnknownMaterial, and so do faces without a material. None restores both.
+    pub fn set_default_material(&mut self, color: Option<Vector3<f64>>) {
+        self.default_material = color;
+    }
+
+    /// Faces of materials with an emission (Ke) in the obj files parsed after this also become
+    /// lights with the given number of samples, one light per material and file.
+    /// None makes them only glow.
+    pub fn set_mesh_lights(&mut self, samples: Option<u32>) {
+        self.mesh_light_samples = samples;
+    }
+
     /// Parse a wavefront obj or a wavefront mtl file
     /// Decide the parser to use in function of the extension
     pub fn parse(&mut self, path: String) -> Result<(), Error> {
@@ -57,10 +110,10 @@ impl FileParser {
 
         // choose the parser
         if path.contains(".obj") {
-            self.parse_obj(contents)?;
+            self.parse_obj(contents, &path)?;
             Ok(())
         } else if path.contains(".mtl") {
-            self.parse_mtl(contents)?;
+            self.parse_mtl(contents, &path)?;
             Ok(())
         } else {
             Err(Error::from(
@@ -69,84 +122,732 @@ impl FileParser {
         }
     }
 
-    /// Parse a wavefront obj file (only supports a subset from the subset that the crate
-    /// wavefront_obj support.
-    fn parse_obj(&mut self, contents: String) -> Result<(), Error> {
-        let obj_set = obj_parse(contents)?;
-
-        if let Some(material) = obj_set.material_library {
-            self.parse(material);
-        }
-        let mut last_material = None;
-        for object in obj_set.objects {
-            for geometry in object.geometry {
-                for shape in geometry.shapes {
-                    let primitive = &shape.primitive;
-                    match primitive {
-                        Primitive::Triangle(u, v, w) => {
-                            let vertices_a = object.vertices[u.0];
-                            let a = Vector3::new(vertices_a.x, vertices_a.y, vertices_a.z);
-
-                            let vertices_b = object.vertices[v.0];
-                            let b = Vector3::new(vertices_b.x, vertices_b.y, vertices_b.z);
-
-                            let vertices_c = object.vertices[w.0];
-                            let c = Vector3::new(vertices_c.x, vertices_c.y, vertices_c.z);
-
-                            let shader = if let Some(name) = &geometry.material_name {
-                                let mat = self.materials.get(name)
-                                    .expect(&format!("Material don't exist: {}", name));
-                                last_material = Some(mat);
-                                material_to_shader(mat)
-                            } else {
-                                if let Some(mat) = last_material {
-                                    material_to_shader(mat)
-                                } else { 
-                                    Ok(get_phong(Vector3::new(0.0, 1.0, 0.0)))
-                                }
-                            }?;
-
-                            self.elements.add_bounded(Box::new(Triangle { a, b, c, shader }));
+    /// Parse a wavefront obj file. Faces become triangle meshes, one per object and group,
+    /// lines and points are skipped with a warning.
+    fn parse_obj(&mut self, contents: String, path: &str) -> Result<(), Error> {
+        // the arrays of the whole file, faces may refer to any vertex before them
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+
+        let mut object_name: Option<String> = None;
+        let mut mesh = MeshBuilder::new(path.to_string());
+        // faces without a material use the one before them, even in other objects
+        let mut material: Option<String> = None;
+        let mut material_line = 0;
+        let mut skipped_lines = 0;
+        let mut skipped_points = 0;
+        let mut unknown_statements = Vec::new();
+        // triangles of the emissive materials, in the order the materials are first used
+        let mut emitters: Vec<Emitter> = Vec::new();
+        for (number, line) in contents.lines().enumerate() {
+            let error = |message: String| Error::File {
+                path: path.to_string(),
+                line: number + 1,
+                message,
+            };
+            let line = line.split('#').next().unwrap_or("");
+            let mut words = line.split_whitespace();
+            let keyword = match words.next() {
+                Some(keyword) => keyword,
+                None => continue,
+            };
+            let arguments: Vec<&str> = words.collect();
+            match keyword {
+                "v" => {
+                    let v = parse_vector(&arguments).map_err(error)?;
+                    positions.push(self.transform.transform_point(&Point3::from(v)).coords);
+                }
+                "vn" => {
+                    let n = parse_vector(&arguments).map_err(error)?;
+                    normals.push((self.normal_matrix * n).normalize());
+                }
+                "vt" => {
+                    let u = parse_number(arguments.get(0)).map_err(error)?;
+                    let v = match arguments.get(1) {
+                        Some(_) => parse_number(arguments.get(1)).map_err(error)?,
+                        None => 0.0,
+                    };
+                    uvs.push(Vector2::new(u, v));
+                }
+                "f" => {
+                    let corners = arguments
+                        .iter()
+                        .map(|corner| {
+                            parse_corner(corner, positions.len(), uvs.len(), normals.len())
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(error)?;
+                    if corners.len() < 3 {
+                        return Err(error(String::from("A face needs at least 3 corners")));
+                    }
+                    let material_index = match mesh.material_index(&material) {
+                        Some(index) => index,
+                        None => {
+                            let shader = self.shader_for(&material, path, material_line)?;
+                            mesh.add_material(&material, shader)
+                        }
+                    };
+                    let points: Vec<_> = corners.iter().map(|c| positions[c.0]).collect();
+                    let triangles = triangulate(&points);
+                    for &[a, b, c] in &triangles {
+                        mesh.add_face(
+                            [corners[a], corners[b], corners[c]],
+                            material_index,
+                            (&positions, &normals, &uvs),
+                        );
+                    }
+                    if let Some(emitter) = self.emitting(&material) {
+                        let triangles = triangles
+                            .iter()
+                            .map(|&[a, b, c]| [points[a], points[b], points[c]]);
+                        match emitters.iter_mut().find(|e| e.material == emitter.material) {
+                            Some(existing) => existing.triangles.extend(triangles),
+                            None => emitters.push(Emitter {
+                                triangles: triangles.collect(),
+                                ..emitter
+                            }),
                         }
-                        _ => ()
+                    }
+                }
+                "l" => skipped_lines += 1,
+                "p" => skipped_points += arguments.len(),
+                "o" => {
+                    self.add_mesh(mesh);
+                    let name = arguments.join(" ");
+                    mesh = MeshBuilder::new(name.clone());
+                    object_name = Some(name);
+                }
+                "g" => {
+                    self.add_mesh(mesh);
+                    // several names put the faces in several groups, that's kept as one name
+                    let group = arguments.join(" ");
+                    let name = match (&object_name, group.is_empty()) {
+                        (Some(object), true) => object.clone(),
+                        (Some(object), false) => format!("{}/{}", object, group),
+                        (None, true) => path.to_string(),
+                        (None, false) => group,
                     };
+                    mesh = MeshBuilder::new(name);
+                }
+                "usemtl" => {
+                    material = Some(arguments.join(" "));
+                    material_line = number + 1;
                 }
+                "mtllib" => {
+                    // relative to the obj file
+                    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+                    for library in arguments {
+                        let library_path = dir.join(library).to_string_lossy().into_owned();
+                        self.parse(library_path).map_err(|library_error| match library_error {
+                            // the error of the mtl file already says where it is
+                            Error::File { .. } => library_error,
+                            _ => error(format!("Material library {}: {}", library, library_error)),
+                        })?;
+                    }
+                }
+                // smoothing groups come from the vertex normals
+                "s" => {}
+                _ => {
+                    if !unknown_statements.contains(&keyword) {
+                        unknown_statements.push(keyword);
+                    }
+                }
+            }
+        }
+        self.add_mesh(mesh);
+
+        for emitter in emitters {
+            let light = MeshLight::new(emitter.triangles, emitter.radiance, emitter.samples);
+            if let Some(light) = light {
+                self.lights.push(Box::new(light));
             }
         }
 
+        if skipped_lines > 0 || skipped_points > 0 {
+            self.warnings.push(format!(
+                "{}: skipped {} lines and {} points, only faces are rendered",
+                path, skipped_lines, skipped_points
+            ));
+        }
+        if !unknown_statements.is_empty() {
+            self.warnings.push(format!(
+                "{}: skipped unsupported statements {}",
+                path,
+                unknown_statements.join(", ")
+            ));
+        }
         Ok(())
     }
 
-    /// Parse a wavefront mnt file
-    fn parse_mtl(&mut self, contents: String) -> Result<(), Error> {
-        let material_set = mtl_parse(contents)?;
+    /// Shader for the faces that use the material, line is where the obj file selects it
+    fn shader_for(
+        &mut self,
+        material: &Option<String>,
+        path: &str,
+        line: usize,
+    ) -> Result<Box<Shader>, Error> {
+        let name = match material {
+            Some(name) => name,
+            None => {
+                let color = self.default_material.unwrap_or(Vector3::new(0.0, 1.0, 0.0));
+                return Ok(get_phong(color));
+            }
+        };
+        match (self.materials.get(name), self.default_material) {
+            (Some(mat), _) => {
+                // every material the mtl parser read has an illum line
+                let model = self.illumination_models.get(name).cloned().unwrap_or(2);
+                Ok(material_to_shader(mat, model, self.textures.get(name), &mut self.warnings))
+            }
+            (None, Some(color)) => {
+                let warning = format!("{}:{}: Material {} doesn't exist", path, line, name);
+                if !self.warnings.contains(&warning) {
+                    self.warnings.push(warning);
+                }
+                Ok(get_phong(color))
+            }
+            (None, None) => Err(Error::UnknownMaterial {
+                path: path.to_string(),
+                line,
+                name: name.clone(),
+            }),
+        }
+    }
+
+    /// The mesh light of the material, still without triangles, if its faces become one
+    fn emitting(&self, material: &Option<String>) -> Option<Emitter> {
+        let samples = self.mesh_light_samples?;
+        let name = material.as_ref()?;
+        match self.materials.get(name)?.color_emissive {
+            Some(emissive) if !is_black(emissive) => Some(Emitter {
+                material: name.clone(),
+                radiance: color_to_vec(emissive),
+                samples,
+                triangles: Vec::new(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Every object and group becomes its own object in the Collector
+    fn add_mesh(&mut self, mesh: MeshBuilder) {
+        if !mesh.faces.is_empty() {
+            self.elements.begin_object(mesh.name.clone());
+            self.elements.add_mesh(mesh.build());
+        }
+    }
+
+    /// Parse a wavefront mnt file, texture maps are loaded relative to its path
+    fn parse_mtl(&mut self, contents: String, path: &str) -> Result<(), Error> {
+        let (contents, illumination_models) = extract_illumination_models(&contents);
+        self.illumination_models.extend(illumination_models);
+        let material_set = mtl_parse(contents).map_err(|error| Error::File {
+            path: path.to_string(),
+            line: error.line_number,
+            message: error.message,
+        })?;
+        let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
         for material in material_set.materials {
             let name = material.name.clone();
+            if let Some(map) = &material.uv_map {
+                let texture = Texture::open(&dir.join(map).to_string_lossy())?;
+                self.textures.insert(name.clone(), Arc::new(texture));
+            }
             self.materials.insert(name, material);
         }
         Ok(())
     }
 }
 
+/// Returns the illumination models by material name and replaces the ones above 3 by 2,
+/// so the mtl parser reads the materials
+fn extract_illumination_models(contents: &str) -> (String, HashMap<String, usize>) {
+    let mut models = HashMap::new();
+    let mut material = None;
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("newmtl"), name) => material = name,
+            (Some("illum"), Some(model)) => {
+                if let (Some(name), Ok(model)) = (material, model.parse::<usize>()) {
+                    models.insert(name.to_string(), model);
+                    if model > 3 {
+                        lines.push("illum 2");
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        lines.push(line);
+    }
+    (lines.join("\n"), models)
+}
+
+/// Emissive faces of one material, they become a MeshLight
+struct Emitter {
+    material: String,
+    radiance: Vector3<f64>,
+    samples: u32,
+    triangles: Vec<[Vector3<f64>; 3]>,
+}
+
+/// Corner of a face: indices of the position, texture coordinates and normal
+type Corner = (usize, Option<usize>, Option<usize>);
+
+/// Faces of one object or group, with the parts of the file's arrays they use
+struct MeshBuilder {
+    name: String,
+    vertices: Vec<Vector3<f64>>,
+    normals: Vec<Vector3<f64>>,
+    uvs: Vec<Vector2<f64>>,
+    /// Index in the file to index in the mesh
+    vertex_indices: HashMap<usize, u32>,
+    normal_indices: HashMap<usize, u32>,
+    uv_indices: HashMap<usize, u32>,
+    faces: Vec<Face>,
+    /// Every material gets one shader per mesh, that all its faces share
+    materials: Vec<Box<Shader>>,
+    material_indices: HashMap<Option<String>, u32>,
+}
+
+impl MeshBuilder {
+    fn new(name: String) -> Self {
+        MeshBuilder {
+            name,
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            vertex_indices: HashMap::new(),
+            normal_indices: HashMap::new(),
+            uv_indices: HashMap::new(),
+            faces: Vec::new(),
+            materials: Vec::new(),
+            material_indices: HashMap::new(),
+        }
+    }
+
+    fn material_index(&self, material: &Option<String>) -> Option<u32> {
+        self.material_indices.get(material).cloned()
+    }
+
+    fn add_material(&mut self, material: &Option<String>, shader: Box<Shader>) -> u32 {
+        self.materials.push(shader);
+        let index = self.materials.len() as u32 - 1;
+        self.material_indices.insert(material.clone(), index);
+        index
+    }
+
+    /// The corners index the arrays of the file
+    fn add_face(
+        &mut self,
+        corners: [Corner; 3],
+        material: u32,
+        (positions, normals, uvs): (&[Vector3<f64>], &[Vector3<f64>], &[Vector2<f64>]),
+    ) {
+        let mut vertices = [0; 3];
+        let mut face_normals = [0; 3];
+        let mut face_uvs = [0; 3];
+        let (mut has_normals, mut has_uvs) = (true, true);
+        for (i, &(vertex, uv, normal)) in corners.iter().enumerate() {
+            vertices[i] = local_index(
+                &mut self.vertex_indices,
+                &mut self.vertices,
+                positions,
+                vertex,
+            );
+            match normal {
+                Some(n) => {
+                    face_normals[i] =
+                        local_index(&mut self.normal_indices, &mut self.normals, normals, n)
+                }
+                None => has_normals = false,
+            }
+            match uv {
+                Some(t) => face_uvs[i] = local_index(&mut self.uv_indices, &mut self.uvs, uvs, t),
+                None => has_uvs = false,
+            }
+        }
+        self.faces.push(Face {
+            vertices,
+            normals: if has_normals {
+                Some(face_normals)
+            } else {
+                None
+            },
+            uvs: if has_uvs { Some(face_uvs) } else { None },
+            material,
+        });
+    }
+
+    fn build(self) -> TriangleMesh {
+        TriangleMesh::new(
+            self.vertices,
+            self.normals,
+            self.uvs,
+            self.faces,
+            self.materials,
+        )
+    }
+}
+
+/// Index in the mesh's array of an element of the file's array, it's copied on first use
+fn local_index<T: Copy>(
+    indices: &mut HashMap<usize, u32>,
+    local: &mut Vec<T>,
+    global: &[T],
+    index: usize,
+) -> u32 {
+    *indices.entry(index).or_insert_with(|| {
+        local.push(global[index]);
+        local.len() as u32 - 1
+    })
+}
+
+fn parse_number(word: Option<&&str>) -> Result<f64, String> {
+    match word {
+        Some(word) => word
+            .parse()
+            .map_err(|_| format!("Expected a number, got {}", word)),
+        None => Err(String::from("Expected a number")),
+    }
+}
+
+fn parse_vector(arguments: &[&str]) -> Result<Vector3<f64>, String> {
+    Ok(Vector3::new(
+        parse_number(arguments.get(0))?,
+        parse_number(arguments.get(1))?,
+        parse_number(arguments.get(2))?,
+    ))
+}
+
+/// Index starting at 0 from an obj index, which starts at 1 or counts back from the last
+/// element if negative
+fn parse_index(word: &str, count: usize) -> Result<usize, String> {
+    let index: isize = word
+        .parse()
+        .map_err(|_| format!("Expected an index, got {}", word))?;
+    let resolved = if index < 0 {
+        count as isize + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as isize {
+        Err(format!(
+            "Index {} is out of range, there are {} elements",
+            index, count
+        ))
+    } else {
+        Ok(resolved as usize)
+    }
+}
+
+/// Corner of a face as v, v/vt, v//vn or v/vt/vn
+fn parse_corner(
+    word: &str,
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+) -> Result<Corner, String> {
+    let mut parts = word.split('/');
+    let vertex = parse_index(parts.next().unwrap_or(""), positions)?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(uv) => Some(parse_index(uv, uvs)?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(normal) => Some(parse_index(normal, normals)?),
+    };
+    Ok((vertex, uv, normal))
+}
+
+/// Splits a polygon into triangles by ear clipping, so concave polygons work too. The
+/// triangles keep the winding of the polygon and refer to its corners by index.
+fn triangulate(points: &[Vector3<f64>]) -> Vec<[usize; 3]> {
+    let fan = || (1..points.len() - 1).map(|i| [0, i, i + 1]).collect();
+    if points.len() == 3 {
+        return vec![[0, 1, 2]];
+    }
+    // Newell's method, works for concave polygons
+    let mut normal = Vector3::zeros();
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        normal += Vector3::new(
+            (a.y - b.y) * (a.z + b.z),
+            (a.z - b.z) * (a.x + b.x),
+            (a.x - b.x) * (a.y + b.y),
+        );
+    }
+    if normal.norm() == 0.0 {
+        return fan();
+    }
+    // project to the plane of the two other axes, the polygon is counterclockwise there
+    let axis = normal.iamax();
+    let (x, y) = ((axis + 1) % 3, (axis + 2) % 3);
+    let sign = normal[axis].signum();
+    let projected: Vec<Vector2<f64>> = points.iter().map(|p| Vector2::new(p[x], p[y])).collect();
+    let cross = |o: usize, a: usize, b: usize| {
+        let (oa, ob) = (projected[a] - projected[o], projected[b] - projected[o]);
+        sign * (oa.x * ob.y - oa.y * ob.x)
+    };
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            cross(a, b, c) > 0.0
+                && remaining.iter().all(|&p| {
+                    p == a
+                        || p == b
+                        || p == c
+                        || !(cross(a, b, p) >= 0.0
+                            && cross(b, c, p) >= 0.0
+                            && cross(c, a, p) >= 0.0)
+                })
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([
+                    remaining[(i + n - 1) % n],
+                    remaining[i],
+                    remaining[(i + 1) % n],
+                ]);
+                remaining.remove(i);
+            }
+            // degenerate polygon, e.g. self intersecting
+            None => return fan(),
+        }
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
 fn color_to_vec(color: Color) -> Vector3<f64> {
     Vector3::new(color.r, color.g, color.b)
 }
 
-fn material_to_shader(material: &Material) -> Result<Box<Shader>, Error> {
-    let diffuse_shader: Box<Shader> = Box::new(DiffuseShader {
-        color: color_to_vec(material.color_diffuse),
+/// Shader of a material by its illumination model as in the mtl specification.
+/// texture is the loaded diffuse map (map_Kd) of the material, it is multiplied with Kd.
+/// Features that aren't supported fall back to the nearest model with a warning.
+fn material_to_shader(
+    material: &Material,
+    illumination_model: usize,
+    texture: Option<&Arc<Texture>>,
+    warnings: &mut Vec<String>,
+) -> Box<Shader> {
+    // every mesh that uses the material builds its shader, but it is reported once
+    let mut warn = |message: &str| {
+        let warning = format!(
+            "Material {} (illum {}): {}",
+            material.name, illumination_model, message
+        );
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    };
+    let shader = match illumination_model {
+        0..=2 => dissolve(material, lit_shader(material, illumination_model, texture)),
+        3 => dissolve(material, lit_shader(material, 2, texture) + reflection(material)),
+        8 => {
+            warn("reflection maps are not supported, the reflections are ray traced");
+            dissolve(material, lit_shader(material, 2, texture) + reflection(material))
+        }
+        4..=7 | 9 => {
+            if illumination_model == 9 {
+                warn("reflection maps are not supported, the reflections are ray traced");
+            }
+            // 5 and 7 with the exact Fresnel equations
+            let fresnel = if illumination_model % 2 == 1 {
+                Fresnel::Exact
+            } else {
+                Fresnel::Schlick
+            };
+            let glass = DielectricShader::new(
+                material.optical_density.unwrap_or(1.0),
+                fresnel,
+                Vector3::new(1.0, 1.0, 1.0),
+            );
+            // 6 and 7 always refract, the others are only as transparent as the dissolve says
+            let opacity = if illumination_model == 6 || illumination_model == 7 {
+                0.0
+            } else {
+                material.alpha
+            };
+            blend(opacity, lit_shader(material, 2, texture), glass)
+        }
+        10 => {
+            warn("shadow mattes are not supported, rendered like illum 2");
+            dissolve(material, lit_shader(material, 2, texture))
+        }
+        _ => {
+            warn("unknown illumination model, rendered like illum 2");
+            dissolve(material, lit_shader(material, 2, texture))
+        }
+    };
+    match material.color_emissive {
+        Some(emissive) if !is_black(emissive) => {
+            shader + EmissiveShader::new(color_to_vec(emissive))
+        }
+        _ => shader,
+    }
+}
+
+/// Illumination models 0 (flat Kd), 1 (Ka and Kd) and 2 (Ka, Kd and a highlight of Ks with
+/// the exponent Ns)
+fn lit_shader(material: &Material, model: usize, texture: Option<&Arc<Texture>>) -> Box<Shader> {
+    let with_texture = |shader: Box<Shader>| match texture {
+        Some(texture) => {
+            TextureShader::new(texture.clone(), TextureFilter::Bilinear, WrapMode::Repeat) * shader
+        }
+        None => shader,
+    };
+    if model == 0 {
+        return with_texture(Box::new(MonochromeShader {
+            color: color_to_vec(material.color_diffuse),
+        }));
+    }
+    let diffuse_shader = with_texture(DiffuseShader::new(color_to_vec(material.color_diffuse)));
+    let ambient_shader = AmbientShader::new(color_to_vec(material.color_ambient));
+    let shader = 0.5 * diffuse_shader + 0.5 * ambient_shader;
+    if model == 1 || is_black(material.color_specular) {
+        return shader;
+    }
+    let specular_color: Box<Shader> = Box::new(MonochromeShader {
+        color: color_to_vec(material.color_specular),
     });
-    let specular_shader = SpecularShader { alpha: 10.0 }; // TODO FIXME use material.color_diffuse
-    let ambient_shader: Box<Shader> = Box::new(AmbientShader {
-        color: color_to_vec(material.color_ambient),
+    // an exponent of 0 would light the whole surface
+    shader + specular_color * SpecularShader::new(material.specular_coefficient.max(1.0))
+}
+
+/// Mirror reflection weighted by Ks
+fn reflection(material: &Material) -> Box<Shader> {
+    let specular_color: Box<Shader> = Box::new(MonochromeShader {
+        color: color_to_vec(material.color_specular),
     });
-    match material.illumination {
-        Illumination::Ambient => Ok(ambient_shader),
-        Illumination::AmbientDiffuse => Ok(0.5 * diffuse_shader + 0.5 * ambient_shader),
-        Illumination::AmbientDiffuseSpecular => {
-            Ok(0.5 * diffuse_shader + specular_shader + 0.5 * ambient_shader)
-        }
-        Illumination::Reflection => Ok(Box::new(MirrorShader { initial_step: 1.0 })),
-        _ => Err(Error::from("Illumination not yet supported")),
+    specular_color * MirrorShader::new()
+}
+
+/// Lets the light behind the surface through as the dissolve d says
+fn dissolve(material: &Material, shader: Box<Shader>) -> Box<Shader> {
+    // a dielectric with the index of refraction 1 neither bends nor reflects
+    let behind = DielectricShader::new(1.0, Fresnel::Exact, Vector3::new(1.0, 1.0, 1.0));
+    blend(material.alpha, shader, behind)
+}
+
+/// opacity * shader + (1 - opacity) * transparent, without the parts that have no weight
+fn blend(opacity: f64, shader: Box<Shader>, transparent: Box<Shader>) -> Box<Shader> {
+    if opacity >= 1.0 {
+        shader
+    } else if opacity <= 0.0 {
+        transparent
+    } else {
+        opacity * shader + (1.0 - opacity) * transparent
+    }
+}
+
+fn is_black(color: Color) -> bool {
+    color.r == 0.0 && color.g == 0.0 && color.b == 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Twice the signed area of the triangle in the xy plane
+    fn signed_area(points: &[Vector3<f64>], [a, b, c]: [usize; 3]) -> f64 {
+        let (ab, ac) = (points[b] - points[a], points[c] - points[a]);
+        ab.x * ac.y - ab.y * ac.x
+    }
+
+    /// Triangles that cover the counterclockwise polygon without overlapping or leaving it
+    fn assert_covers(points: &[Vector3<f64>], area: f64) {
+        let triangles = triangulate(points);
+        assert_eq!(triangles.len(), points.len() - 2);
+        for &triangle in &triangles {
+            assert!(signed_area(points, triangle) > 0.0, "{:?} is flipped", triangle);
+        }
+        let total: f64 = triangles.iter().map(|&t| signed_area(points, t)).sum();
+        assert!((total / 2.0 - area).abs() < 1e-12);
+    }
+
+    #[test]
+    fn negative_indices_count_back() {
+        assert_eq!(parse_index("-1", 5), Ok(4));
+        assert_eq!(parse_index("-5", 5), Ok(0));
+        assert_eq!(parse_index("1", 5), Ok(0));
+    }
+
+    #[test]
+    fn out_of_range_indices() {
+        assert!(parse_index("0", 5).is_err());
+        assert!(parse_index("6", 5).is_err());
+        assert!(parse_index("-6", 5).is_err());
+
+        let mut parser = FileParser::new();
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4\n";
+        match parser.parse_obj(obj.to_string(), "test.obj") {
+            Err(Error::File { line, .. }) => assert_eq!(line, 5),
+            _ => panic!("expected an error in line 5"),
+        }
+    }
+
+    #[test]
+    fn corners_without_uv() {
+        assert_eq!(parse_corner("3//2", 4, 0, 2), Ok((2, None, Some(1))));
+        assert_eq!(parse_corner("-1/1/-2", 4, 1, 2), Ok((3, Some(0), Some(0))));
+        assert_eq!(parse_corner("2", 4, 0, 0), Ok((1, None, None)));
+        assert!(parse_corner("1//3", 4, 0, 2).is_err());
+    }
+
+    #[test]
+    fn material_warnings_once() {
+        let mut parser = FileParser::new();
+        let mtl = "newmtl chrome\nNs 10\nKa 0 0 0\nKd 1 1 1\nKs 1 1 1\nd 1\nillum 8\n";
+        parser.parse_mtl(mtl.to_string(), "test.mtl").unwrap();
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl chrome\ng a\nf 1 2 3\ng b\nf 1 2 3\n";
+        parser.parse_obj(obj.to_string(), "test.obj").unwrap();
+        assert_eq!(parser.warnings.len(), 1);
+    }
+
+    #[test]
+    fn parse_faces_with_relative_indices() {
+        let mut parser = FileParser::new();
+        let obj = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf -4//1 -3//1 -2//1 -1//1\n";
+        parser.parse_obj(obj.to_string(), "test.obj").unwrap();
+        assert_eq!(parser.elements.get_primitive_count(), 2);
+    }
+
+    #[test]
+    fn concave_quad_is_not_a_fan() {
+        // the fan from the first corner would cover the notch at the second corner
+        let points = [
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(0.5, 0.5, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+        ];
+        assert_covers(&points, 1.0);
+    }
+
+    #[test]
+    fn l_shaped_polygon_is_not_a_fan() {
+        let points = [
+            Vector3::new(2.0, 1.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(1.0, 2.0, 0.0),
+            Vector3::new(0.0, 2.0, 0.0),
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+        ];
+        assert_covers(&points, 3.0);
     }
 }
@@ -0,0 +1,111 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::output::{Channel, PixelType};
+use half::f16;
+use std::io::{Result, Write};
+
+/// Write an uncompressed scanline OpenEXR file with the given channels.
+/// Channel names may contain a layer prefix, e.g. `depth.Z`.
+pub fn write<W: Write>(
+    writer: &mut W,
+    width: u32,
+    height: u32,
+    channels: &[Channel],
+    pixel_type: PixelType,
+) -> Result<()> {
+    // the channel list and the pixel data have to be sorted by name
+    let mut channels: Vec<&Channel> = channels.iter().collect();
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    let (type_id, bytes_per_value): (i32, u64) = match pixel_type {
+        PixelType::Half => (1, 2),
+        PixelType::Float => (2, 4),
+    };
+
+    let mut header = Vec::new();
+    // magic number and version 2, single part scanline file
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+    let mut channel_list = Vec::new();
+    for channel in &channels {
+        channel_list.extend_from_slice(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend_from_slice(&type_id.to_le_bytes());
+        // pLinear and three reserved bytes
+        channel_list.extend_from_slice(&[0, 0, 0, 0]);
+        // x and y sampling
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+        channel_list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    attribute(&mut header, "channels", "chlist", &channel_list);
+
+    // no compression
+    attribute(&mut header, "compression", "compression", &[0]);
+
+    let mut window = Vec::new();
+    for v in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    // increasing y
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_bits().to_le_bytes());
+    let mut center = Vec::new();
+    center.extend_from_slice(&0f32.to_bits().to_le_bytes());
+    center.extend_from_slice(&0f32.to_bits().to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &center);
+    attribute(&mut header, "screenWindowWidth", "float", &1f32.to_bits().to_le_bytes());
+    header.push(0);
+    writer.write_all(&header)?;
+
+    // offset table, every scanline is its own block
+    let line_size = width as u64 * channels.len() as u64 * bytes_per_value;
+    let first_line = header.len() as u64 + 8 * height as u64;
+    for y in 0..height as u64 {
+        writer.write_all(&(first_line + y * (8 + line_size)).to_le_bytes())?;
+    }
+
+    let mut line = Vec::with_capacity(line_size as usize);
+    for y in 0..height as usize {
+        line.clear();
+        for channel in &channels {
+            let row = &channel.values[y * width as usize..(y + 1) * width as usize];
+            for value in row {
+                match pixel_type {
+                    PixelType::Half => {
+                        line.extend_from_slice(&f16::from_f32(*value).to_bits().to_le_bytes())
+                    }
+                    PixelType::Float => line.extend_from_slice(&value.to_bits().to_le_bytes()),
+                }
+            }
+        }
+        writer.write_all(&(y as i32).to_le_bytes())?;
+        writer.write_all(&(line.len() as i32).to_le_bytes())?;
+        writer.write_all(&line)?;
+    }
+    writer.flush()
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
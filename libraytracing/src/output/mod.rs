@@ -0,0 +1,130 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::framebuffer::{FrameBuffer, Layers};
+use crate::pass::Pass;
+use std::fs::File;
+use std::io::{self, BufWriter, ErrorKind};
+use std::path::Path;
+
+pub mod exr;
+pub mod pfm;
+pub mod radiance;
+
+/// Largest number of pixels the pfm and hdr readers accept, so that a broken header can't
+/// make them allocate arbitrary amounts of memory
+pub const MAX_PIXELS: usize = 1 << 27;
+
+/// Precision of the channels in an OpenEXR file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelType {
+    Half,
+    Float,
+}
+
+/// A named image channel, row by row with the top row first
+pub struct Channel<'a> {
+    pub name: String,
+    pub values: &'a [f32],
+}
+
+/// Whether the file extension is one of the float formats (exr, pfm, hdr)
+pub fn is_float_format(path: &str) -> bool {
+    match extension(path).as_ref().map(String::as_str) {
+        Some("exr") | Some("pfm") | Some("hdr") => true,
+        _ => false,
+    }
+}
+
+/// Save the linear colors of the layers without quantization, the format is chosen from the
+/// extension of path. OpenEXR files get all passes as extra channels (e.g. `depth.Z`),
+/// for the other formats every pass is saved in its own file next to the image.
+pub fn save(layers: &Layers, path: &str, pixel_type: PixelType) -> Result<(), Error> {
+    match extension(path).as_ref().map(String::as_str) {
+        Some("exr") => {
+            let mut channel_values = Vec::new();
+            let beauty_channels = ["R", "G", "B"];
+            for (i, name) in beauty_channels.iter().enumerate() {
+                channel_values.push((name.to_string(), component(&layers.beauty, i)));
+            }
+            for (pass, buffer) in &layers.passes {
+                for (i, name) in pass.get_channels().iter().enumerate() {
+                    let name = format!("{}.{}", pass.get_name(), name);
+                    channel_values.push((name, component(buffer, i)));
+                }
+            }
+            let channels: Vec<Channel> = channel_values
+                .iter()
+                .map(|(name, values)| Channel {
+                    name: name.clone(),
+                    values,
+                })
+                .collect();
+            let frame = &layers.beauty;
+            let mut writer = BufWriter::new(File::create(path)?);
+            exr::write(&mut writer, frame.width, frame.height, &channels, pixel_type)?;
+        }
+        Some("pfm") | Some("hdr") => {
+            save_frame(&layers.beauty, path)?;
+            for (pass, buffer) in &layers.passes {
+                save_frame(buffer, &pass_path(path, *pass))?;
+            }
+        }
+        _ => return Err(Error::from(format!("{} is not an exr, pfm or hdr file", path))),
+    }
+    Ok(())
+}
+
+/// File name for a pass saved next to the image, e.g. out.depth.png for out.png
+pub fn pass_path(path: &str, pass: Pass) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, pass.get_name(), extension.to_string_lossy()),
+        None => format!("{}.{}", stem, pass.get_name()),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+fn save_frame(frame: &FrameBuffer, path: &str) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match extension(path).as_ref().map(String::as_str) {
+        Some("pfm") => pfm::write(&mut writer, frame)?,
+        _ => radiance::write(&mut writer, frame)?,
+    }
+    Ok(())
+}
+
+fn component(frame: &FrameBuffer, i: usize) -> Vec<f32> {
+    frame.get_pixels().iter().map(|p| p[i]).collect()
+}
+
+fn extension(path: &str) -> Option<String> {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+/// Check the size from the header of an image that is about to be read
+fn check_size(width: usize, height: usize) -> io::Result<()> {
+    let invalid = |message| Err(io::Error::new(ErrorKind::InvalidData, message));
+    match width.checked_mul(height) {
+        Some(0) => invalid("empty image"),
+        Some(pixels) if pixels <= MAX_PIXELS => Ok(()),
+        _ => invalid("image too large"),
+    }
+}
@@ -0,0 +1,136 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::framebuffer::FrameBuffer;
+use na::Vector3;
+use std::io::{BufRead, Error, ErrorKind, Result, Write};
+
+/// Write a Portable Float Map: a small text header followed by
+/// little endian f32 RGB triples, bottom row first
+pub fn write<W: Write>(writer: &mut W, frame: &FrameBuffer) -> Result<()> {
+    // a negative scale means little endian
+    write!(writer, "PF\n{} {}\n-1.0\n", frame.width, frame.height)?;
+    for y in (0..frame.height).rev() {
+        for x in 0..frame.width {
+            let pixel = frame.get_pixel(x, y);
+            for value in pixel.iter() {
+                writer.write_all(&value.to_bits().to_le_bytes())?;
+            }
+        }
+    }
+    writer.flush()
+}
+
+/// Read a color (PF) or grayscale (Pf) Portable Float Map, top row first
+pub fn read<R: BufRead>(reader: &mut R) -> Result<(usize, usize, Vec<Vector3<f32>>)> {
+    let channels = match read_word(reader)?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid("not a Portable Float Map")),
+    };
+    let width: usize = read_word(reader)?.parse().map_err(|_| invalid("invalid width"))?;
+    let height: usize = read_word(reader)?.parse().map_err(|_| invalid("invalid height"))?;
+    let scale: f32 = read_word(reader)?.parse().map_err(|_| invalid("invalid scale"))?;
+    super::check_size(width, height)?;
+    let mut rows = vec![Vec::with_capacity(width); height];
+    let mut bytes = [0u8; 4];
+    let mut value = |reader: &mut R| -> Result<f32> {
+        reader.read_exact(&mut bytes)?;
+        Ok(if scale < 0.0 {
+            f32::from_le_bytes(bytes)
+        } else {
+            f32::from_be_bytes(bytes)
+        })
+    };
+    // bottom row first
+    for row in rows.iter_mut().rev() {
+        for _ in 0..width {
+            row.push(if channels == 3 {
+                Vector3::new(value(reader)?, value(reader)?, value(reader)?)
+            } else {
+                Vector3::repeat(value(reader)?)
+            });
+        }
+    }
+    Ok((width, height, rows.concat()))
+}
+
+/// Next word of the header and the single whitespace after it
+fn read_word<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut word = String::new();
+    let mut byte = [0u8; 1];
+    loop {
+        reader.read_exact(&mut byte)?;
+        if byte[0].is_ascii_whitespace() {
+            if word.is_empty() {
+                continue;
+            }
+            return Ok(word);
+        }
+        word.push(byte[0] as char);
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn write_read_round_trip() {
+        let mut frame = FrameBuffer::new(3, 2);
+        for y in 0..2 {
+            for x in 0..3 {
+                let value = (y * 3 + x) as f32;
+                frame.put_pixel(x, y, Vector3::new(value, -value, 1000.0 * value + 0.5));
+            }
+        }
+        let mut file = Vec::new();
+        write(&mut file, &frame).unwrap();
+
+        let (width, height, pixels) = read(&mut Cursor::new(file)).unwrap();
+        assert_eq!((width, height), (3, 2));
+        assert_eq!(pixels, frame.get_pixels());
+    }
+
+    #[test]
+    fn read_big_endian_grayscale() {
+        let mut file = b"Pf\n2 1\n1.0\n".to_vec();
+        file.extend_from_slice(&0.5f32.to_be_bytes());
+        file.extend_from_slice(&2.0f32.to_be_bytes());
+
+        let (width, height, pixels) = read(&mut Cursor::new(file)).unwrap();
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(pixels, vec![Vector3::repeat(0.5), Vector3::repeat(2.0)]);
+    }
+
+    #[test]
+    fn reject_empty_image() {
+        assert!(read(&mut Cursor::new(b"PF\n0 4\n-1.0\n".to_vec())).is_err());
+    }
+
+    #[test]
+    fn reject_huge_image() {
+        let error = read(&mut Cursor::new(b"PF\n100000 100000\n-1.0\n".to_vec())).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        let overflowing = format!("PF\n{} 2\n-1.0\n", usize::MAX);
+        assert!(read(&mut Cursor::new(overflowing.into_bytes())).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::framebuffer::FrameBuffer;
+use na::Vector3;
+use std::io::{BufRead, Error, ErrorKind, Result, Write};
+
+/// Write a Radiance RGBE file with flat (not run length encoded) scanlines
+pub fn write<W: Write>(writer: &mut W, frame: &FrameBuffer) -> Result<()> {
+    write!(
+        writer,
+        "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+        frame.height, frame.width
+    )?;
+    for y in 0..frame.height {
+        for x in 0..frame.width {
+            writer.write_all(&to_rgbe(frame.get_pixel(x, y)))?;
+        }
+    }
+    writer.flush()
+}
+
+/// Shared exponent encoding: three 8 bit mantissas and the exponent of the largest channel
+fn to_rgbe(color: Vector3<f32>) -> [u8; 4] {
+    let color = color.map(|c| c.max(0.0) as f64);
+    let max = color.x.max(color.y).max(color.z);
+    if max < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // max = mantissa * 2^exponent with mantissa in [0.5, 1)
+    let mut exponent = max.log2().floor() as i32 + 1;
+    if max / 2f64.powi(exponent) >= 1.0 {
+        exponent += 1;
+    }
+    let scale = 256.0 / 2f64.powi(exponent);
+    [
+        (color.x * scale).min(255.0) as u8,
+        (color.y * scale).min(255.0) as u8,
+        (color.z * scale).min(255.0) as u8,
+        (exponent + 128).min(255).max(0) as u8,
+    ]
+}
+
+/// Read a Radiance RGBE file with flat or run length encoded scanlines, top row first.
+/// Only the standard orientation (-Y height +X width) is supported.
+pub fn read<R: BufRead>(reader: &mut R) -> Result<(usize, usize, Vec<Vector3<f32>>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid("not a Radiance file"));
+    }
+    // the header ends with an empty line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("missing resolution"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with("FORMAT=") && line != "FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid("only the rgbe format is supported"));
+        }
+    }
+    line.clear();
+    reader.read_line(&mut line)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (height, width) = match words.as_slice() {
+        ["-Y", height, "+X", width] => match (height.parse(), width.parse()) {
+            (Ok(height), Ok(width)) => (height, width),
+            _ => return Err(invalid("invalid resolution")),
+        },
+        _ => return Err(invalid("only -Y height +X width is supported")),
+    };
+    super::check_size(width, height)?;
+    let mut pixels = Vec::with_capacity(width * height);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        read_scanline(reader, &mut scanline)?;
+        pixels.extend(scanline.iter().map(|&rgbe| from_rgbe(rgbe)));
+    }
+    Ok((width, height, pixels))
+}
+
+fn read_scanline<R: BufRead>(reader: &mut R, scanline: &mut [[u8; 4]]) -> Result<()> {
+    let mut start = [0u8; 4];
+    reader.read_exact(&mut start)?;
+    let width = scanline.len();
+    let run_length_encoded = (8..0x8000).contains(&width)
+        && start[0] == 2
+        && start[1] == 2
+        && (usize::from(start[2]) << 8 | usize::from(start[3])) == width;
+    if !run_length_encoded {
+        scanline[0] = start;
+        for pixel in scanline.iter_mut().skip(1) {
+            reader.read_exact(pixel)?;
+        }
+        return Ok(());
+    }
+    // the four channels one after another, each as runs and literal sequences
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 2];
+            reader.read_exact(&mut count[..1])?;
+            let (run, length) = if count[0] > 128 {
+                (true, usize::from(count[0] - 128))
+            } else {
+                (false, usize::from(count[0]))
+            };
+            if length == 0 || x + length > width {
+                return Err(invalid("invalid run length encoding"));
+            }
+            if run {
+                reader.read_exact(&mut count[1..])?;
+                for pixel in &mut scanline[x..x + length] {
+                    pixel[channel] = count[1];
+                }
+            } else {
+                for pixel in &mut scanline[x..x + length] {
+                    reader.read_exact(&mut count[1..])?;
+                    pixel[channel] = count[1];
+                }
+            }
+            x += length;
+        }
+    }
+    Ok(())
+}
+
+fn from_rgbe(rgbe: [u8; 4]) -> Vector3<f32> {
+    if rgbe[3] == 0 {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+    // the middle of the range the mantissa was rounded down from
+    let scale = 2f64.powi(i32::from(rgbe[3]) - 136);
+    Vector3::new(
+        ((f64::from(rgbe[0]) + 0.5) * scale) as f32,
+        ((f64::from(rgbe[1]) + 0.5) * scale) as f32,
+        ((f64::from(rgbe[2]) + 0.5) * scale) as f32,
+    )
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn write_read_round_trip() {
+        let colors = [
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.25, 0.5, 1.0),
+            Vector3::new(3.5, 0.01, 2.0),
+            Vector3::new(1000.0, 250.0, 12.0),
+        ];
+        let mut frame = FrameBuffer::new(2, 2);
+        for (i, &color) in colors.iter().enumerate() {
+            frame.put_pixel(i as u32 % 2, i as u32 / 2, color);
+        }
+        let mut file = Vec::new();
+        write(&mut file, &frame).unwrap();
+
+        let (width, height, pixels) = read(&mut Cursor::new(file)).unwrap();
+        assert_eq!((width, height), (2, 2));
+        for (pixel, color) in pixels.iter().zip(colors.iter()) {
+            // the mantissas have 8 bits, relative to the largest channel
+            let tolerance = color.max() / 128.0;
+            assert!((pixel - color).amax() <= tolerance, "{} != {}", pixel, color);
+        }
+    }
+
+    #[test]
+    fn read_run_length_encoded() {
+        let mut file = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n".to_vec();
+        file.extend_from_slice(&[2, 2, 0, 8]);
+        // red: a run of 8, green: 8 literal values, blue: two runs, exponent: a run
+        file.extend_from_slice(&[128 + 8, 128]);
+        file.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        file.extend_from_slice(&[128 + 3, 0, 128 + 5, 255]);
+        file.extend_from_slice(&[128 + 8, 129]);
+
+        let (width, height, pixels) = read(&mut Cursor::new(file)).unwrap();
+        assert_eq!((width, height), (8, 1));
+        let expected: Vec<_> = (0..8)
+            .map(|x| from_rgbe([128, 16 * x as u8, if x < 3 { 0 } else { 255 }, 129]))
+            .collect();
+        assert_eq!(pixels, expected);
+        assert_eq!(pixels[0], Vector3::new(1.0039062, 0.00390625, 0.00390625));
+    }
+
+    #[test]
+    fn reject_empty_image() {
+        let file = b"#?RADIANCE\n\n-Y 2 +X 0\n".to_vec();
+        assert!(read(&mut Cursor::new(file)).is_err());
+    }
+
+    #[test]
+    fn reject_huge_image() {
+        let file = b"#?RADIANCE\n\n-Y 100000 +X 100000\n".to_vec();
+        let error = read(&mut Cursor::new(file)).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,165 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::framebuffer::FrameBuffer;
+use crate::ray::Ray;
+use crate::tone_mapping::linear_to_srgb;
+use crate::world::World;
+use image::{DynamicImage, ImageBuffer, Rgb};
+use na::Vector3;
+use std::f32;
+use std::str::FromStr;
+
+/// Arbitrary output variables a camera can render next to the image.
+/// They are taken from the first intersection of the ray through the pixel center.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pass {
+    /// Distance from the camera to the hit, infinite for misses
+    Depth,
+    /// Normalized world space normal
+    Normal,
+    /// pos_on_surface of the hit
+    Uv,
+    /// Color of the shader without lighting
+    Albedo,
+    /// Id the Collector gave the primitive
+    PrimitiveId,
+    /// Id of the object the primitive belongs to
+    ObjectId,
+}
+
+pub const ALL_PASSES: [Pass; 6] = [
+    Pass::Depth,
+    Pass::Normal,
+    Pass::Uv,
+    Pass::Albedo,
+    Pass::PrimitiveId,
+    Pass::ObjectId,
+];
+
+impl Pass {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Pass::Depth => "depth",
+            Pass::Normal => "normal",
+            Pass::Uv => "uv",
+            Pass::Albedo => "albedo",
+            Pass::PrimitiveId => "primitive_id",
+            Pass::ObjectId => "object_id",
+        }
+    }
+
+    /// Names of the used channels of the frame buffer, in order
+    pub fn get_channels(&self) -> &'static [&'static str] {
+        match self {
+            Pass::Depth => &["Z"],
+            Pass::Normal => &["X", "Y", "Z"],
+            Pass::Uv => &["U", "V"],
+            Pass::Albedo => &["R", "G", "B"],
+            Pass::PrimitiveId | Pass::ObjectId => &["id"],
+        }
+    }
+
+    /// Value of the pass for the first thing the ray hits
+    pub fn evaluate(&self, world: &World, ray: &Ray) -> Vector3<f32> {
+        let (dist, intersection) = match world.intercept(ray) {
+            Some(hit) => hit,
+            None => {
+                return match self {
+                    Pass::Depth => Vector3::new(f32::INFINITY, 0.0, 0.0),
+                    _ => Vector3::new(0.0, 0.0, 0.0),
+                };
+            }
+        };
+        let value = match self {
+            Pass::Depth => Vector3::new(dist, 0.0, 0.0),
+            Pass::Normal => intersection.shading_normal.normalize(),
+            Pass::Uv => Vector3::new(
+                intersection.pos_on_surface.x,
+                intersection.pos_on_surface.y,
+                0.0,
+            ),
+            Pass::Albedo => intersection
+                .shader
+                .get_albedo_for(intersection.pos_on_surface),
+            Pass::PrimitiveId => Vector3::new(intersection.primitive_id as f64, 0.0, 0.0),
+            Pass::ObjectId => Vector3::new(intersection.object_id as f64, 0.0, 0.0),
+        };
+        value.map(|v| v as f32)
+    }
+
+    /// False color image to look at the pass: depth is brighter near the camera,
+    /// normals are mapped from [-1, 1] to [0, 1], uvs repeat every unit
+    /// and ids get random colors
+    pub fn to_image(&self, frame: &FrameBuffer) -> DynamicImage {
+        let max_depth = frame
+            .get_pixels()
+            .iter()
+            .map(|p| p.x)
+            .filter(|d| d.is_finite())
+            .fold(0.0, f32::max);
+        let buffer = ImageBuffer::from_fn(frame.width, frame.height, |x, y| {
+            let value = frame.get_pixel(x, y);
+            let color = match self {
+                Pass::Depth => {
+                    let brightness = if value.x.is_finite() && max_depth > 0.0 {
+                        1.0 - value.x / max_depth
+                    } else {
+                        0.0
+                    };
+                    Vector3::new(brightness, brightness, brightness)
+                }
+                Pass::Normal => value.map(|v| v * 0.5 + 0.5),
+                Pass::Uv => Vector3::new(fract(value.x), fract(value.y), 0.0),
+                Pass::Albedo => value.map(|v| linear_to_srgb(v.min(1.0).max(0.0) as f64) as f32),
+                Pass::PrimitiveId | Pass::ObjectId => id_color(value.x as u32),
+            };
+            let rgb = color.map(|c| (c.min(1.0).max(0.0) * 255.0).round() as u8);
+            Rgb([rgb.x, rgb.y, rgb.z])
+        });
+        DynamicImage::ImageRgb8(buffer)
+    }
+}
+
+impl FromStr for Pass {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        ALL_PASSES
+            .iter()
+            .find(|pass| pass.get_name() == name)
+            .cloned()
+            .ok_or_else(|| Error::from(format!("Unknown pass: {}", name)))
+    }
+}
+
+fn fract(v: f32) -> f32 {
+    v - v.floor()
+}
+
+/// Bright random color for an id, black for 0
+fn id_color(id: u32) -> Vector3<f32> {
+    if id == 0 {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+    let h = id.wrapping_mul(0x9e37_79b9);
+    Vector3::new(
+        0.2 + 0.8 * ((h >> 24) & 0xff) as f32 / 255.0,
+        0.2 + 0.8 * ((h >> 16) & 0xff) as f32 / 255.0,
+        0.2 + 0.8 * ((h >> 8) & 0xff) as f32 / 255.0,
+    )
+}
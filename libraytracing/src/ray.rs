@@ -16,8 +16,43 @@
 
 use na::Unit;
 use na::Vector3;
+use std::f64;
 
+/// Distance a ray leaving a surface has to travel before a hit counts,
+/// so it doesn't hit the surface it starts on again
+pub const RAY_EPSILON: f64 = 0.0001;
+
+#[derive(Clone, Copy)]
 pub struct Ray {
     pub dir: Unit<Vector3<f64>>,
     pub start: Vector3<f64>,
+    /// Only hits at a distance t with t_min < t < t_max from start count
+    pub t_min: f64,
+    pub t_max: f64,
+}
+
+impl Ray {
+    /// Ray from a point that isn't on a surface, e.g. the camera
+    pub fn new(start: Vector3<f64>, dir: Unit<Vector3<f64>>) -> Self {
+        Ray {
+            dir,
+            start,
+            t_min: 0.0,
+            t_max: f64::INFINITY,
+        }
+    }
+
+    /// Ray leaving the surface at start
+    pub fn secondary(start: Vector3<f64>, dir: Unit<Vector3<f64>>) -> Self {
+        Ray {
+            dir,
+            start,
+            t_min: RAY_EPSILON,
+            t_max: f64::INFINITY,
+        }
+    }
+
+    pub fn contains(&self, t: f64) -> bool {
+        self.t_min < t && t < self.t_max
+    }
 }
@@ -0,0 +1,1165 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::camera::equilinear_camera::EquilinearCamera;
+use crate::camera::equirectangular_camera::EquirectangularCamera;
+use crate::camera::filter::{BoxFilter, Filter, GaussianFilter, MitchellFilter, TentFilter};
+use crate::camera::sampler::{HaltonSampler, JitteredSampler, RegularSampler, Sampler, SobolSampler};
+use crate::camera::Camera;
+use crate::error::Error;
+use crate::obj::FileParser;
+use crate::shader::additive_shader::AdditiveShader;
+use crate::shader::ambient_shader::AmbientShader;
+use crate::shader::chess_shader::ChessShader;
+use crate::shader::dielectric_shader::DielectricShader;
+use crate::shader::diffuse_shader::DiffuseShader;
+use crate::shader::emissive_shader::EmissiveShader;
+use crate::shader::mirror_shader::MirrorShader;
+use crate::shader::monochrome_shader::MonochromeShader;
+use crate::shader::multiplicative_shader::MultiplicativeShader;
+use crate::shader::specular_shader::SpecularShader;
+use crate::shader::texture_shader::{Texture, TextureShader};
+use crate::shader::{get_phong, Shader};
+use crate::storage::bv_storage::Builder;
+use crate::storage::collector::Collector;
+use crate::storage::Bounded;
+use crate::world::area_light::{DiskLight, RectangleLight, SphereLight};
+use crate::world::background::{
+    Background, EnvironmentLight, EnvironmentMap, GradientBackground, SolidBackground,
+};
+use crate::world::instance::Instance;
+use crate::world::light::{DirectionalLight, Falloff, Light, PointLight, SpotLight};
+use crate::world::plane::Plane;
+use crate::world::sky::Sky;
+use crate::world::sphere::Sphere;
+use crate::world::triangle::Triangle;
+use crate::world::World;
+use na::{Unit, Vector2, Vector3};
+use serde_derive::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+/// A scene read from a TOML scene description file:
+///
+/// ```toml
+/// [camera]
+/// type = "equilinear"
+/// width = 1200
+/// height = 800
+/// pos = [200.0, 0.0, 300.0]
+/// look_at = [0.0, 0.0, 0.0]
+/// sampler = { type = "jittered", samples = 16 }
+/// filter = { type = "gaussian" }
+///
+/// [[light]]
+/// pos = [0.0, -10.0, 6.0]
+/// color = [1.0, 0.5, 1.0]
+///
+/// [[light]]
+/// type = "spot"
+/// pos = [0.0, 0.0, 10.0]
+/// dir = [0.0, 0.0, -1.0]
+/// color = [1.0, 1.0, 1.0]
+/// intensity = 50.0
+/// falloff = "inverse_square"
+/// inner_angle = 20.0
+/// outer_angle = 30.0
+///
+/// [[sphere]]
+/// center = [1.0, 1.0, 4.0]
+/// radius = 1.0
+/// shader = { type = "phong", color = [1.0, 0.0, 0.0] }
+///
+/// [[obj]]
+/// path = "ducky.obj"
+/// ```
+///
+/// Planes (`[[plane]]` with `a`, `b`, `c`) and triangles (`[[triangle]]` with `a`, `b`, `c`)
+/// work like spheres. Shaders are trees of `phong`, `ambient`, `diffuse`, `specular`,
+/// `monochrome`, `emissive`, `mirror`, `chess`, `sum`, `scale` and `product` nodes. Lights are
+/// `point`, `directional` (with `dir` instead of `pos`) or `spot` lights, or `sphere` (`pos`,
+/// `radius`), `rectangle` (corners `a`, `b`, `c`) and `disk` (`pos`, `dir`, `radius`) area lights
+/// that cast soft shadows with `samples` shadow rays. The optional `[background]` is a `color`,
+/// a `gradient`, an `environment` image or a daylight `sky` with a sun light. Environments and
+/// skies also light the scene if they have `light_samples`.
+pub struct Scene {
+    pub elements: Collector,
+    pub lights: Vec<Box<Light>>,
+    pub camera: Box<Camera>,
+    /// What rays see that don't hit anything
+    pub background: Box<Background>,
+    /// Warnings of the obj files, with the entry that loaded them
+    pub warnings: Vec<String>,
+}
+
+impl Scene {
+    /// Load a scene description file, obj files are searched relative to it
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let base_dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+        Self::parse(&contents, base_dir)
+    }
+
+    /// Build a scene from the content of a scene description file
+    pub fn parse(contents: &str, base_dir: &Path) -> Result<Self, Error> {
+        let description: SceneDescription = toml::from_str(contents)?;
+
+        let mut file_parser = FileParser::new();
+        for (i, obj) in description.objs.iter().enumerate() {
+            let entry = format!("obj[{}] ({})", i, obj.path);
+            let path = base_dir.join(&obj.path).to_string_lossy().into_owned();
+            file_parser.set_default_material(obj.default_material.map(to_vector));
+            if obj.mesh_light_samples == Some(0) {
+                return Err(scene_error(&entry, "mesh_light_samples must be positive"));
+            }
+            file_parser.set_mesh_lights(obj.mesh_light_samples);
+            file_parser.parse(path).map_err(|error| scene_error(&entry, error))?;
+        }
+        let mut warnings = file_parser.warnings;
+        let mut elements = file_parser.elements;
+        let mesh_lights = file_parser.lights;
+
+        for (i, sphere) in description.spheres.iter().enumerate() {
+            let entry = format!("sphere[{}]", i);
+            elements.begin_object(entry.clone());
+            if sphere.radius <= 0.0 {
+                return Err(scene_error(&entry, "radius must be positive"));
+            }
+            elements.add_bounded(Box::new(Sphere {
+                center: to_vector(sphere.center),
+                radius: sphere.radius,
+                shader: sphere.shader.build(&entry, base_dir)?,
+                roll: sphere.roll,
+                pitch: sphere.pitch,
+                yaw: sphere.yaw,
+            }));
+        }
+
+        for (i, plane) in description.planes.iter().enumerate() {
+            let entry = format!("plane[{}]", i);
+            elements.begin_object(entry.clone());
+            let (a, b, c) = (to_vector(plane.a), to_vector(plane.b), to_vector(plane.c));
+            if (b - a).cross(&(c - a)).norm() == 0.0 {
+                return Err(scene_error(&entry, "a, b and c must not lie on one line"));
+            }
+            elements.add(Box::new(Plane {
+                a,
+                b,
+                c,
+                shader: plane.shader.build(&entry, base_dir)?,
+            }));
+        }
+
+        for (i, triangle) in description.triangles.iter().enumerate() {
+            let entry = format!("triangle[{}]", i);
+            elements.begin_object(entry.clone());
+            elements.add_bounded(Box::new(Triangle {
+                a: to_vector(triangle.a),
+                b: to_vector(triangle.b),
+                c: to_vector(triangle.c),
+                uvs: triangle
+                    .uv
+                    .map(|uv| [to_vector2(uv[0]), to_vector2(uv[1]), to_vector2(uv[2])]),
+                shader: triangle.shader.build(&entry, base_dir)?,
+            }));
+        }
+
+        // the parsed obj files with their number of primitives
+        let mut prototypes: HashMap<&str, (Arc<Bounded>, usize)> = HashMap::new();
+        for (i, instance) in description.instances.iter().enumerate() {
+            let entry = format!("instance[{}] ({})", i, instance.path);
+            if !prototypes.contains_key(instance.path.as_str()) {
+                let mut file_parser = FileParser::new();
+                let path = base_dir.join(&instance.path).to_string_lossy().into_owned();
+                file_parser
+                    .parse(path)
+                    .map_err(|error| scene_error(&entry, error))?;
+                for warning in file_parser.warnings {
+                    warnings.push(format!("{}: {}", entry, warning));
+                }
+                let count = file_parser.elements.get_primitive_count();
+                let storage = Arc::new(file_parser.elements.into_bounded_storage());
+                prototypes.insert(&instance.path, (storage, count));
+            }
+            let (object, count) = &prototypes[instance.path.as_str()];
+            let transform = Instance::transform_from(
+                to_vector(instance.translation),
+                instance.roll,
+                instance.pitch,
+                instance.yaw,
+                to_vector(instance.scale),
+            );
+            let instance =
+                Instance::new(object.clone(), transform).map_err(|error| scene_error(&entry, error))?;
+            elements.begin_object(entry);
+            elements.add_instance(instance, *count);
+        }
+
+        let mut lights = Vec::new();
+        for (i, light) in description.lights.iter().enumerate() {
+            let entry = format!("light[{}]", i);
+            let light = light.build(&entry)?;
+            let geometry = light.geometry();
+            if !geometry.is_empty() {
+                elements.begin_object(entry);
+                for shape in geometry {
+                    elements.add_bounded(shape);
+                }
+            }
+            lights.push(light);
+        }
+        lights.extend(mesh_lights);
+
+        let background = match &description.background {
+            Some(background) => {
+                let (background, light) = background.build(base_dir)?;
+                lights.extend(light);
+                background
+            }
+            None => SolidBackground::new(Vector3::new(0.0, 0.0, 0.0)),
+        };
+
+        Ok(Scene {
+            elements,
+            lights,
+            camera: description.camera.build()?,
+            background,
+            warnings,
+        })
+    }
+
+    /// World with the elements in a BVStorage and the instances in an InstanceStorage, so
+    /// they can still be moved, and the camera to render it with
+    pub fn into_world(self) -> (World, Box<Camera>) {
+        let (elements, instances) = self.elements.into_storages(&Builder::default());
+        let mut world = World::with_instances(Box::new(elements), instances, self.lights);
+        world.set_background(self.background);
+        (world, self.camera)
+    }
+}
+
+fn scene_error<T: ToString>(entry: &str, message: T) -> Error {
+    Error::Scene {
+        entry: String::from(entry),
+        message: message.to_string(),
+    }
+}
+
+fn to_vector(v: [f64; 3]) -> Vector3<f64> {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+fn to_vector2(v: [f64; 2]) -> Vector2<f64> {
+    Vector2::new(v[0], v[1])
+}
+
+fn default_viewangle() -> f64 {
+    40.0
+}
+
+fn default_recursion_depth() -> f64 {
+    10.0
+}
+
+fn default_samples() -> u32 {
+    1
+}
+
+fn default_box_radius() -> f64 {
+    0.5
+}
+
+fn default_tent_radius() -> f64 {
+    1.0
+}
+
+fn default_gaussian_radius() -> f64 {
+    1.5
+}
+
+fn default_alpha() -> f64 {
+    2.0
+}
+
+fn default_mitchell_radius() -> f64 {
+    2.0
+}
+
+fn default_mitchell_parameter() -> f64 {
+    1.0 / 3.0
+}
+
+fn default_light_type() -> String {
+    String::from("point")
+}
+
+fn default_light_samples() -> u32 {
+    16
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+fn default_up() -> [f64; 3] {
+    [0.0, -1.0, 0.0]
+}
+
+fn default_turbidity() -> f64 {
+    3.0
+}
+
+fn default_ground_albedo() -> [f64; 3] {
+    [0.2, 0.2, 0.2]
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+fn default_texture_filter() -> String {
+    String::from("bilinear")
+}
+
+fn default_wrap() -> String {
+    String::from("repeat")
+}
+
+fn default_fresnel() -> String {
+    String::from("exact")
+}
+
+fn default_transmission() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_scale() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_size() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    camera: CameraDescription,
+    background: Option<BackgroundDescription>,
+    #[serde(default, rename = "light")]
+    lights: Vec<LightDescription>,
+    #[serde(default, rename = "sphere")]
+    spheres: Vec<SphereDescription>,
+    #[serde(default, rename = "plane")]
+    planes: Vec<PlaneDescription>,
+    #[serde(default, rename = "triangle")]
+    triangles: Vec<TriangleDescription>,
+    #[serde(default, rename = "obj")]
+    objs: Vec<ObjDescription>,
+    #[serde(default, rename = "instance")]
+    instances: Vec<InstanceDescription>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum BackgroundDescription {
+    Color {
+        color: [f64; 3],
+    },
+    /// From bottom straight down to top straight up, up is -y like at the top of an
+    /// equirectangular image
+    Gradient {
+        bottom: [f64; 3],
+        top: [f64; 3],
+        #[serde(default = "default_up")]
+        up: [f64; 3],
+    },
+    /// Equirectangular image, rotated like an equirectangular camera
+    Environment {
+        path: String,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+        #[serde(default)]
+        roll: f64,
+        #[serde(default)]
+        pitch: f64,
+        #[serde(default)]
+        yaw: f64,
+        /// Also light the scene with this many shadow rays per shading point
+        light_samples: Option<u32>,
+    },
+    /// Daylight sky with a matching sun light
+    Sky {
+        /// Towards the sun
+        sun_dir: [f64; 3],
+        /// Haziness from 2 (clear) to 10 (hazy)
+        #[serde(default = "default_turbidity")]
+        turbidity: f64,
+        #[serde(default = "default_ground_albedo")]
+        ground_albedo: [f64; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+        #[serde(default = "default_up")]
+        up: [f64; 3],
+        /// Also light the scene with the sky besides the sun, with this many shadow rays per
+        /// shading point
+        light_samples: Option<u32>,
+    },
+}
+
+impl BackgroundDescription {
+    /// The background and the lights it brings
+    fn build(&self, base_dir: &Path) -> Result<(Box<Background>, Vec<Box<Light>>), Error> {
+        match *self {
+            BackgroundDescription::Color { color } => {
+                Ok((SolidBackground::new(to_vector(color)), Vec::new()))
+            }
+            BackgroundDescription::Gradient { bottom, top, up } => {
+                let up = to_vector(up);
+                if up.norm() == 0.0 {
+                    return Err(scene_error("background", "up must not be zero"));
+                }
+                let gradient = GradientBackground {
+                    bottom: to_vector(bottom),
+                    top: to_vector(top),
+                    up: Unit::new_normalize(up),
+                };
+                Ok((Box::new(gradient), Vec::new()))
+            }
+            BackgroundDescription::Environment {
+                ref path,
+                intensity,
+                roll,
+                pitch,
+                yaw,
+                light_samples,
+            } => {
+                let texture = Texture::open(&base_dir.join(path).to_string_lossy())
+                    .map_err(|error| scene_error("background", error))?;
+                let map = EnvironmentMap::new(Arc::new(texture), intensity, roll, pitch, yaw);
+                let map = Arc::new(map);
+                let mut lights: Vec<Box<Light>> = Vec::new();
+                if let Some(samples) = check_light_samples(light_samples)? {
+                    lights.push(Box::new(EnvironmentLight::new(map.clone(), samples)));
+                }
+                Ok((Box::new(map), lights))
+            }
+            BackgroundDescription::Sky {
+                sun_dir,
+                turbidity,
+                ground_albedo,
+                intensity,
+                up,
+                light_samples,
+            } => {
+                let sky = Sky::new(
+                    to_vector(sun_dir),
+                    to_vector(up),
+                    turbidity,
+                    to_vector(ground_albedo),
+                    intensity,
+                )
+                .map_err(|error| scene_error("background", error))?;
+                let mut lights: Vec<Box<Light>> = vec![Box::new(sky.sun())];
+                if let Some(samples) = check_light_samples(light_samples)? {
+                    let sky_light = sky
+                        .sky_light(samples)
+                        .map_err(|error| scene_error("background", error))?;
+                    lights.push(Box::new(sky_light));
+                }
+                Ok((Box::new(sky), lights))
+            }
+        }
+    }
+}
+
+fn check_light_samples(samples: Option<u32>) -> Result<Option<u32>, Error> {
+    match samples {
+        Some(0) => Err(scene_error("background", "light_samples must be positive")),
+        samples => Ok(samples),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum CameraDescription {
+    Equilinear {
+        width: u32,
+        height: u32,
+        pos: [f64; 3],
+        #[serde(default)]
+        roll: f64,
+        #[serde(default)]
+        pitch: f64,
+        #[serde(default)]
+        yaw: f64,
+        #[serde(default = "default_viewangle")]
+        vertical_viewangle: f64,
+        look_at: Option<[f64; 3]>,
+        #[serde(default = "default_recursion_depth")]
+        recursion_depth: f64,
+        #[serde(default)]
+        sampler: SamplerDescription,
+        #[serde(default)]
+        filter: FilterDescription,
+    },
+    Equirectangular {
+        height: u32,
+        pos: [f64; 3],
+        #[serde(default)]
+        roll: f64,
+        #[serde(default)]
+        pitch: f64,
+        #[serde(default)]
+        yaw: f64,
+        #[serde(default = "default_recursion_depth")]
+        recursion_depth: f64,
+        #[serde(default)]
+        sampler: SamplerDescription,
+        #[serde(default)]
+        filter: FilterDescription,
+    },
+}
+
+impl CameraDescription {
+    fn build(&self) -> Result<Box<Camera>, Error> {
+        match *self {
+            CameraDescription::Equilinear {
+                width,
+                height,
+                pos,
+                roll,
+                pitch,
+                yaw,
+                vertical_viewangle,
+                look_at,
+                recursion_depth,
+                ref sampler,
+                ref filter,
+            } => {
+                if width == 0 || height == 0 {
+                    return Err(scene_error("camera", "width and height must be positive"));
+                }
+                let mut camera = EquilinearCamera {
+                    width,
+                    height,
+                    roll,
+                    pitch,
+                    yaw,
+                    pos: to_vector(pos),
+                    vertical_viewangle,
+                    recursion_depth,
+                    sampler: sampler.build(),
+                    filter: filter.build()?,
+                };
+                if let Some(target) = look_at {
+                    camera.point_at(to_vector(target));
+                }
+                Ok(Box::new(camera))
+            }
+            CameraDescription::Equirectangular {
+                height,
+                pos,
+                roll,
+                pitch,
+                yaw,
+                recursion_depth,
+                ref sampler,
+                ref filter,
+            } => {
+                if height == 0 {
+                    return Err(scene_error("camera", "height must be positive"));
+                }
+                Ok(Box::new(EquirectangularCamera {
+                    height,
+                    roll,
+                    pitch,
+                    yaw,
+                    pos: to_vector(pos),
+                    recursion_depth,
+                    sampler: sampler.build(),
+                    filter: filter.build()?,
+                }))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum SamplerDescription {
+    Regular {
+        #[serde(default = "default_samples")]
+        samples: u32,
+    },
+    Jittered {
+        #[serde(default = "default_samples")]
+        samples: u32,
+    },
+    Halton {
+        #[serde(default = "default_samples")]
+        samples: u32,
+    },
+    Sobol {
+        #[serde(default = "default_samples")]
+        samples: u32,
+    },
+}
+
+impl Default for SamplerDescription {
+    fn default() -> Self {
+        SamplerDescription::Regular { samples: 1 }
+    }
+}
+
+impl SamplerDescription {
+    fn build(&self) -> Box<Sampler> {
+        match *self {
+            SamplerDescription::Regular { samples } => RegularSampler::new(samples),
+            SamplerDescription::Jittered { samples } => JitteredSampler::new(samples),
+            SamplerDescription::Halton { samples } => HaltonSampler::new(samples),
+            SamplerDescription::Sobol { samples } => SobolSampler::new(samples),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum FilterDescription {
+    Box {
+        #[serde(default = "default_box_radius")]
+        radius: f64,
+    },
+    Tent {
+        #[serde(default = "default_tent_radius")]
+        radius: f64,
+    },
+    Gaussian {
+        #[serde(default = "default_gaussian_radius")]
+        radius: f64,
+        #[serde(default = "default_alpha")]
+        alpha: f64,
+    },
+    Mitchell {
+        #[serde(default = "default_mitchell_radius")]
+        radius: f64,
+        #[serde(default = "default_mitchell_parameter")]
+        b: f64,
+        #[serde(default = "default_mitchell_parameter")]
+        c: f64,
+    },
+}
+
+impl Default for FilterDescription {
+    fn default() -> Self {
+        FilterDescription::Box { radius: 0.5 }
+    }
+}
+
+impl FilterDescription {
+    fn build(&self) -> Result<Box<Filter>, Error> {
+        let filter: Box<Filter> = match *self {
+            FilterDescription::Box { radius } => Box::new(BoxFilter { radius }),
+            FilterDescription::Tent { radius } => Box::new(TentFilter { radius }),
+            FilterDescription::Gaussian { radius, alpha } => {
+                Box::new(GaussianFilter { radius, alpha })
+            }
+            FilterDescription::Mitchell { radius, b, c } => {
+                Box::new(MitchellFilter { radius, b, c })
+            }
+        };
+        if filter.get_radius() <= 0.0 {
+            return Err(scene_error("camera", "filter radius must be positive"));
+        }
+        Ok(filter)
+    }
+}
+
+/// A light is a `point` light unless `type` says otherwise, so the fields that only some types
+/// use are optional here and checked in `build`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDescription {
+    #[serde(rename = "type", default = "default_light_type")]
+    kind: String,
+    pos: Option<[f64; 3]>,
+    dir: Option<[f64; 3]>,
+    color: [f64; 3],
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+    falloff: Option<FalloffDescription>,
+    /// Half angles of the cone of a spot light in degrees
+    inner_angle: Option<f64>,
+    outer_angle: Option<f64>,
+    radius: Option<f64>,
+    /// Corners of a rectangle light
+    a: Option<[f64; 3]>,
+    b: Option<[f64; 3]>,
+    c: Option<[f64; 3]>,
+    /// Shadow rays per shading point of area lights
+    #[serde(default = "default_light_samples")]
+    samples: u32,
+    /// Whether area lights are seen by camera rays
+    #[serde(default = "default_visible")]
+    visible: bool,
+}
+
+/// Either `"none"`, `"inverse_square"` or the coefficients `[constant, linear, quadratic]`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FalloffDescription {
+    Named(String),
+    Polynomial([f64; 3]),
+}
+
+impl LightDescription {
+    fn build(&self, entry: &str) -> Result<Box<Light>, Error> {
+        let color = to_vector(self.color);
+        let intensity = self.intensity;
+        if intensity < 0.0 {
+            return Err(scene_error(entry, "intensity must not be negative"));
+        }
+        match self.kind.as_str() {
+            "point" => Ok(Box::new(PointLight {
+                pos: self.pos(entry)?,
+                color,
+                intensity,
+                falloff: self.falloff(entry)?,
+            })),
+            "directional" => {
+                if self.pos.is_some() {
+                    return Err(scene_error(entry, "a directional light has no pos"));
+                }
+                Ok(Box::new(DirectionalLight {
+                    dir: self.dir(entry)?,
+                    color,
+                    intensity,
+                }))
+            }
+            "spot" => {
+                let outer_angle = match self.outer_angle {
+                    Some(angle) => angle,
+                    None => return Err(scene_error(entry, "a spot light needs an outer_angle")),
+                };
+                let inner_angle = self.inner_angle.unwrap_or(outer_angle);
+                if inner_angle.is_nan()
+                    || outer_angle.is_nan()
+                    || inner_angle < 0.0
+                    || inner_angle > outer_angle
+                    || outer_angle >= 180.0
+                {
+                    return Err(scene_error(
+                        entry,
+                        "angles must satisfy 0 <= inner_angle <= outer_angle < 180",
+                    ));
+                }
+                Ok(Box::new(SpotLight {
+                    pos: self.pos(entry)?,
+                    dir: self.dir(entry)?,
+                    color,
+                    intensity,
+                    falloff: self.falloff(entry)?,
+                    inner_angle: inner_angle.to_radians(),
+                    outer_angle: outer_angle.to_radians(),
+                }))
+            }
+            "sphere" => Ok(Box::new(SphereLight {
+                center: self.pos(entry)?,
+                radius: self.radius(entry)?,
+                color,
+                intensity,
+                samples: self.samples(entry)?,
+                visible: self.visible,
+            })),
+            "rectangle" => {
+                let corner = |corner: Option<[f64; 3]>| {
+                    corner.map(to_vector).ok_or_else(|| {
+                        scene_error(entry, "a rectangle light needs the corners a, b and c")
+                    })
+                };
+                let (a, b, c) = (corner(self.a)?, corner(self.b)?, corner(self.c)?);
+                if (b - a).cross(&(c - a)).norm() == 0.0 {
+                    return Err(scene_error(entry, "a, b and c must not lie on one line"));
+                }
+                Ok(Box::new(RectangleLight {
+                    a,
+                    b,
+                    c,
+                    color,
+                    intensity,
+                    samples: self.samples(entry)?,
+                    visible: self.visible,
+                }))
+            }
+            "disk" => Ok(Box::new(DiskLight {
+                center: self.pos(entry)?,
+                normal: self.dir(entry)?,
+                radius: self.radius(entry)?,
+                color,
+                intensity,
+                samples: self.samples(entry)?,
+                visible: self.visible,
+            })),
+            kind => Err(scene_error(entry, format!("Unknown light type {}", kind))),
+        }
+    }
+
+    fn radius(&self, entry: &str) -> Result<f64, Error> {
+        match self.radius {
+            Some(radius) if radius > 0.0 => Ok(radius),
+            Some(_) => Err(scene_error(entry, "radius must be positive")),
+            None => Err(scene_error(entry, format!("a {} light needs a radius", self.kind))),
+        }
+    }
+
+    fn samples(&self, entry: &str) -> Result<u32, Error> {
+        if self.samples == 0 {
+            return Err(scene_error(entry, "samples must be positive"));
+        }
+        Ok(self.samples)
+    }
+
+    fn pos(&self, entry: &str) -> Result<Vector3<f64>, Error> {
+        match self.pos {
+            Some(pos) => Ok(to_vector(pos)),
+            None => Err(scene_error(entry, format!("a {} light needs a pos", self.kind))),
+        }
+    }
+
+    fn dir(&self, entry: &str) -> Result<Unit<Vector3<f64>>, Error> {
+        match self.dir.map(to_vector) {
+            Some(dir) if dir.norm() > 0.0 => Ok(Unit::new_normalize(dir)),
+            Some(_) => Err(scene_error(entry, "dir must not be zero")),
+            None => Err(scene_error(entry, format!("a {} light needs a dir", self.kind))),
+        }
+    }
+
+    fn falloff(&self, entry: &str) -> Result<Falloff, Error> {
+        match self.falloff {
+            None => Ok(Falloff::None),
+            Some(FalloffDescription::Named(ref name)) => match name.as_str() {
+                "none" => Ok(Falloff::None),
+                "inverse_square" => Ok(Falloff::InverseSquare),
+                name => Err(scene_error(entry, format!("Unknown falloff {}", name))),
+            },
+            Some(FalloffDescription::Polynomial([constant, linear, quadratic])) => {
+                let negative = constant < 0.0 || linear < 0.0 || quadratic < 0.0;
+                if negative || constant + linear + quadratic == 0.0 {
+                    return Err(scene_error(
+                        entry,
+                        "falloff coefficients must not be negative and not all zero",
+                    ));
+                }
+                Ok(Falloff::Polynomial {
+                    constant,
+                    linear,
+                    quadratic,
+                })
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDescription {
+    center: [f64; 3],
+    radius: f64,
+    shader: ShaderDescription,
+    #[serde(default)]
+    roll: f64,
+    #[serde(default)]
+    pitch: f64,
+    #[serde(default)]
+    yaw: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaneDescription {
+    a: [f64; 3],
+    b: [f64; 3],
+    c: [f64; 3],
+    shader: ShaderDescription,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleDescription {
+    a: [f64; 3],
+    b: [f64; 3],
+    c: [f64; 3],
+    /// Texture coordinates of a, b and c
+    uv: Option<[[f64; 2]; 3]>,
+    shader: ShaderDescription,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjDescription {
+    path: String,
+    /// Color of a phong shader for faces whose material doesn't exist, they are an error
+    /// without it
+    default_material: Option<[f64; 3]>,
+    /// Faces of materials with an emission (Ke) also light the scene with this many shadow
+    /// rays per shading point, without it they only glow
+    mesh_light_samples: Option<u32>,
+}
+
+/// An obj file placed with a transform, every file is only parsed once
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InstanceDescription {
+    path: String,
+    #[serde(default)]
+    translation: [f64; 3],
+    #[serde(default)]
+    roll: f64,
+    #[serde(default)]
+    pitch: f64,
+    #[serde(default)]
+    yaw: f64,
+    #[serde(default = "default_scale")]
+    scale: [f64; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum ShaderDescription {
+    Phong {
+        color: [f64; 3],
+    },
+    Ambient {
+        color: [f64; 3],
+    },
+    Diffuse {
+        color: [f64; 3],
+    },
+    Specular {
+        alpha: f64,
+    },
+    Monochrome {
+        color: [f64; 3],
+    },
+    Mirror,
+    /// Glows with color * intensity without being lit
+    Emissive {
+        color: [f64; 3],
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    /// Glass and the like, the normals of the surface have to point outside
+    Dielectric {
+        /// Index of refraction
+        ior: f64,
+        /// exact or schlick
+        #[serde(default = "default_fresnel")]
+        fresnel: String,
+        /// Filters the refracted light
+        #[serde(default = "default_transmission")]
+        color: [f64; 3],
+    },
+    /// Image at the texture coordinates, without lighting
+    Texture {
+        path: String,
+        /// nearest or bilinear
+        #[serde(default = "default_texture_filter")]
+        filter: String,
+        /// repeat or clamp
+        #[serde(default = "default_wrap")]
+        wrap: String,
+    },
+    Chess {
+        shader1: Box<ShaderDescription>,
+        shader2: Box<ShaderDescription>,
+        #[serde(default = "default_size")]
+        size: f64,
+    },
+    /// shader1 + shader2 + ...
+    Sum {
+        shaders: Vec<ShaderDescription>,
+    },
+    /// factor * shader
+    Scale {
+        factor: f64,
+        shader: Box<ShaderDescription>,
+    },
+    /// shader1 * shader2 * ... (component wise)
+    Product {
+        shaders: Vec<ShaderDescription>,
+    },
+}
+
+impl ShaderDescription {
+    /// Paths of textures are relative to base_dir
+    fn build(&self, entry: &str, base_dir: &Path) -> Result<Box<Shader>, Error> {
+        Ok(match self {
+            ShaderDescription::Phong { color } => get_phong(to_vector(*color)),
+            ShaderDescription::Ambient { color } => AmbientShader::new(to_vector(*color)),
+            ShaderDescription::Diffuse { color } => DiffuseShader::new(to_vector(*color)),
+            ShaderDescription::Specular { alpha } => SpecularShader::new(*alpha),
+            ShaderDescription::Monochrome { color } => Box::new(MonochromeShader {
+                color: to_vector(*color),
+            }),
+            ShaderDescription::Mirror => MirrorShader::new(),
+            ShaderDescription::Emissive { color, intensity } => {
+                EmissiveShader::new(*intensity * to_vector(*color))
+            }
+            ShaderDescription::Dielectric {
+                ior,
+                fresnel,
+                color,
+            } => {
+                let fresnel = fresnel.parse().map_err(|error| scene_error(entry, error))?;
+                DielectricShader::new(*ior, fresnel, to_vector(*color))
+            }
+            ShaderDescription::Texture { path, filter, wrap } => {
+                let filter = filter.parse().map_err(|error| scene_error(entry, error))?;
+                let wrap = wrap.parse().map_err(|error| scene_error(entry, error))?;
+                let texture = Texture::open(&base_dir.join(path).to_string_lossy())
+                    .map_err(|error| scene_error(entry, error))?;
+                TextureShader::new(Arc::new(texture), filter, wrap)
+            }
+            ShaderDescription::Chess {
+                shader1,
+                shader2,
+                size,
+            } => {
+                if *size <= 0.0 {
+                    return Err(scene_error(entry, "chess size must be positive"));
+                }
+                Box::new(ChessShader {
+                    shader1: shader1.build(entry, base_dir)?,
+                    shader2: shader2.build(entry, base_dir)?,
+                    size: *size,
+                })
+            }
+            ShaderDescription::Scale { factor, shader } => Box::new(MultiplicativeShader {
+                alpha: *factor,
+                shader: shader.build(entry, base_dir)?,
+            }),
+            ShaderDescription::Sum { shaders } => {
+                let mut shaders = shaders.iter();
+                let mut sum = match shaders.next() {
+                    Some(shader) => shader.build(entry, base_dir)?,
+                    None => return Err(scene_error(entry, "sum needs at least one shader")),
+                };
+                for shader in shaders {
+                    sum = Box::new(AdditiveShader {
+                        shader1: sum,
+                        shader2: shader.build(entry, base_dir)?,
+                    });
+                }
+                sum
+            }
+            ShaderDescription::Product { shaders } => {
+                let mut shaders = shaders.iter();
+                let mut product = match shaders.next() {
+                    Some(shader) => shader.build(entry, base_dir)?,
+                    None => return Err(scene_error(entry, "product needs at least one shader")),
+                };
+                for shader in shaders {
+                    product = product * shader.build(entry, base_dir)?;
+                }
+                product
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMERA: &str = "
+        [camera]
+        type = \"equilinear\"
+        width = 40
+        height = 30
+        pos = [0.0, 0.0, -5.0]
+        look_at = [0.0, 0.0, 0.0]
+    ";
+
+    fn parse_error(contents: &str) -> String {
+        match Scene::parse(&format!("{}{}", CAMERA, contents), Path::new("")) {
+            Ok(_) => panic!("the scene has no error"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_minimal_scene() {
+        let contents = "
+            [[light]]
+            pos = [0.0, -10.0, 6.0]
+            color = [1.0, 1.0, 1.0]
+
+            [[sphere]]
+            center = [0.0, 0.0, 0.0]
+            radius = 1.0
+            shader = { type = \"phong\", color = [1.0, 0.0, 0.0] }
+        ";
+        let scene = Scene::parse(&format!("{}{}", CAMERA, contents), Path::new("")).unwrap();
+        assert_eq!(scene.lights.len(), 1);
+        assert_eq!(scene.elements.get_primitive_count(), 1);
+        assert_eq!((scene.camera.get_width(), scene.camera.get_height()), (40, 30));
+        assert!(scene.warnings.is_empty());
+    }
+
+    #[test]
+    fn unknown_field_names_field_and_line() {
+        let error = parse_error("
+            [[light]]
+            type = \"point\"
+            pos = [0.0, -10.0, 6.0]
+            colour = [1.0, 1.0, 1.0]
+        ");
+        assert!(error.starts_with("unknown field `colour`"), "{}", error);
+        assert!(error.ends_with("for key `light` at line 9 column 13"), "{}", error);
+    }
+
+    #[test]
+    fn out_of_range_names_entry() {
+        let spot = "
+            [[light]]
+            pos = [0.0, -10.0, 6.0]
+            color = [1.0, 1.0, 1.0]
+
+            [[light]]
+            type = \"spot\"
+            pos = [0.0, 0.0, 10.0]
+            dir = [0.0, 0.0, -1.0]
+            color = [1.0, 1.0, 1.0]
+            inner_angle = 40.0
+            outer_angle = 30.0
+        ";
+        let message = "light[1]: angles must satisfy 0 <= inner_angle <= outer_angle < 180";
+        assert_eq!(parse_error(spot), message);
+        assert_eq!(parse_error(&spot.replace("30.0", "nan")), message);
+        let sphere = "
+            [[sphere]]
+            center = [0.0, 0.0, 0.0]
+            radius = -1.0
+            shader = { type = \"mirror\" }
+        ";
+        assert_eq!(parse_error(sphere), "sphere[0]: radius must be positive");
+    }
+}
@@ -15,7 +15,7 @@
  * file for more details. **/
 
 use crate::shader::Shader;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use nalgebra::{Vector2, Vector3};
 use std::ops::Add;
 
@@ -31,6 +31,7 @@ impl Shader for AdditiveShader {
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        lights: &VisibleLights,
         surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Vector3<f64> {
@@ -39,6 +40,7 @@ impl Shader for AdditiveShader {
             ray_dir,
             surface_normal,
             world,
+            lights,
             surface_pos,
             recursion_depth,
         );
@@ -47,11 +49,23 @@ impl Shader for AdditiveShader {
             ray_dir,
             surface_normal,
             world,
+            lights,
             surface_pos,
             recursion_depth,
         );
         ap1 + ap2
     }
+
+    /// The albedo of the first shader that has one, blending light terms doesn't change the
+    /// color of the surface
+    fn get_albedo_for(&self, surface_pos: Vector2<f64>) -> Vector3<f64> {
+        let albedo = self.shader1.get_albedo_for(surface_pos);
+        if albedo != Vector3::zeros() {
+            albedo
+        } else {
+            self.shader2.get_albedo_for(surface_pos)
+        }
+    }
 }
 // Add 2 Boxed Shader
 impl Add for Box<Shader> {
@@ -18,7 +18,7 @@ use crate::helpers::polar2vector;
 use crate::helpers::vector2polar;
 use crate::ray::Ray;
 use crate::shader::Shader;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use nalgebra::{Rotation3, Unit, Vector2, Vector3};
 use std::f64;
 
@@ -35,14 +35,10 @@ impl AmbientShader {
         world: &World,
         recursion_depth: f64,
         int_pos: Vector3<f64>,
-        initial_step: f64,
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
     ) -> Vector3<f64> {
-        let ray = Ray {
-            start: int_pos + ray_dir * initial_step,
-            dir: Unit::new_normalize(ray_dir),
-        };
+        let ray = Ray::secondary(int_pos, Unit::new_normalize(ray_dir));
         if let Some((dist, int)) = world.intercept(&ray) {
             (int.get_appearance(ray.dir.into_inner(), world, recursion_depth / 5.0)
                 * (1.0 / (1.0 + dist / 2.0))
@@ -50,7 +46,10 @@ impl AmbientShader {
                 * ray.dir.dot(&surface_normal.normalize()))
             .component_mul(&self.color)
         } else {
-            Vector3::new(0.0, 0.0, 0.0)
+            // the background is infinitely far away, so it is not weakened by the distance
+            (world.background.radiance(ray.dir.into_inner())
+                * ray.dir.dot(&surface_normal.normalize()))
+            .component_mul(&self.color)
         }
     }
 }
@@ -59,16 +58,22 @@ impl Shader for AmbientShader {
     fn get_appearance_for(
         &self,
         intersection_pos: Vector3<f64>,
-        _ray_dir: Vector3<f64>,
+        ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        _lights: &VisibleLights,
         _surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Vector3<f64> {
         if recursion_depth < 1.0 {
             return Vector3::new(0.0, 0.0, 0.0);
         }
-        let initial_step = 0.001;
+        // gather on the side the ray came from
+        let surface_normal = if surface_normal.dot(&ray_dir) > 0.0 {
+            -surface_normal
+        } else {
+            surface_normal
+        };
         let (roll, pitch) = vector2polar(&surface_normal);
         let rot_matrix = Rotation3::from_euler_angles(roll, -pitch + f64::consts::FRAC_PI_2, 0.0);
 
@@ -77,7 +82,6 @@ impl Shader for AmbientShader {
             world,
             recursion_depth,
             intersection_pos,
-            initial_step,
             ray_dir,
             surface_normal,
         );
@@ -92,7 +96,6 @@ impl Shader for AmbientShader {
                 world,
                 recursion_depth,
                 intersection_pos,
-                initial_step,
                 ray_dir,
                 surface_normal,
             );
@@ -107,11 +110,14 @@ impl Shader for AmbientShader {
                 world,
                 recursion_depth,
                 intersection_pos,
-                initial_step,
                 ray_dir,
                 surface_normal,
             );
         }
         appereance / 8.0
     }
+
+    fn get_albedo_for(&self, _surface_pos: Vector2<f64>) -> Vector3<f64> {
+        self.color
+    }
 }
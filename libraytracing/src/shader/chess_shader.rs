@@ -15,7 +15,7 @@
  * file for more details. **/
 
 use crate::shader::Shader;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use nalgebra::{Vector2, Vector3};
 
 pub struct ChessShader {
@@ -42,29 +42,34 @@ impl Shader for ChessShader {
         _ray_dir: Vector3<f64>,
         _surface_normal: Vector3<f64>,
         _world: &World,
+        lights: &VisibleLights,
         _surface_pos: Vector2<f64>,
         _recursion_depth: f64,
     ) -> Vector3<f64> {
-        let modulo = (_surface_pos / self.size).map(|x| if my_mod(x, 2.0) <= 1.0 { 0 } else { 1 });
+        self.choose(_surface_pos).get_appearance_for(
+            _intersection_pos,
+            _ray_dir,
+            _surface_normal,
+            _world,
+            lights,
+            _surface_pos,
+            _recursion_depth,
+        )
+    }
+
+    fn get_albedo_for(&self, surface_pos: Vector2<f64>) -> Vector3<f64> {
+        self.choose(surface_pos).get_albedo_for(surface_pos)
+    }
+}
+
+impl ChessShader {
+    fn choose(&self, surface_pos: Vector2<f64>) -> &Box<Shader> {
+        let modulo = (surface_pos / self.size).map(|x| if my_mod(x, 2.0) <= 1.0 { 0 } else { 1 });
         let chooser = modulo.dot(&modulo);
-        return if chooser == 0 || chooser == 2 {
-            self.shader1.get_appearance_for(
-                _intersection_pos,
-                _ray_dir,
-                _surface_normal,
-                _world,
-                _surface_pos,
-                _recursion_depth,
-            )
+        if chooser == 0 || chooser == 2 {
+            &self.shader1
         } else {
-            self.shader2.get_appearance_for(
-                _intersection_pos,
-                _ray_dir,
-                _surface_normal,
-                _world,
-                _surface_pos,
-                _recursion_depth,
-            )
-        };
+            &self.shader2
+        }
     }
 }
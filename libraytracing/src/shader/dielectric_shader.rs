@@ -0,0 +1,132 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::ray::Ray;
+use crate::shader::Shader;
+use crate::world::{VisibleLights, World};
+use nalgebra::{Unit, Vector2, Vector3};
+use std::str::FromStr;
+
+/// How the light is split between reflection and refraction
+#[derive(Clone, Copy)]
+pub enum Fresnel {
+    /// Fresnel equations for unpolarized light
+    Exact,
+    /// Schlick's approximation
+    Schlick,
+}
+
+impl FromStr for Fresnel {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        match name {
+            "exact" => Ok(Fresnel::Exact),
+            "schlick" => Ok(Fresnel::Schlick),
+            _ => Err(Error::from(format!("Unknown fresnel term {}", name))),
+        }
+    }
+}
+
+impl Fresnel {
+    /// Part of the light that is reflected, eta is the ratio of the index of refraction the
+    /// light comes from to the one it goes into
+    fn reflectance(&self, cos_incident: f64, cos_transmitted: f64, eta: f64) -> f64 {
+        match self {
+            Fresnel::Exact => {
+                let s = (eta * cos_incident - cos_transmitted) / (eta * cos_incident + cos_transmitted);
+                let p = (cos_incident - eta * cos_transmitted) / (cos_incident + eta * cos_transmitted);
+                (s * s + p * p) / 2.0
+            }
+            Fresnel::Schlick => {
+                let r0 = ((1.0 - eta) / (1.0 + eta)).powi(2);
+                // the angle on the optically thinner side
+                let cos = if eta > 1.0 { cos_transmitted } else { cos_incident };
+                r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+            }
+        }
+    }
+}
+
+/// Glass, water and the like: reflects and refracts, the surface normal has to point to
+/// the outside
+pub struct DielectricShader {
+    /// Index of refraction of the inside, the outside has 1
+    pub ior: f64,
+    pub fresnel: Fresnel,
+    /// Filters the refracted light
+    pub color: Vector3<f64>,
+}
+
+impl DielectricShader {
+    pub fn new(ior: f64, fresnel: Fresnel, color: Vector3<f64>) -> Box<Shader> {
+        Box::new(DielectricShader {
+            ior,
+            fresnel,
+            color,
+        })
+    }
+}
+
+impl Shader for DielectricShader {
+    fn get_appearance_for(
+        &self,
+        intersection_pos: Vector3<f64>,
+        ray_dir: Vector3<f64>,
+        surface_normal: Vector3<f64>,
+        world: &World,
+        _lights: &VisibleLights,
+        _surface_pos: Vector2<f64>,
+        recursion_depth: f64,
+    ) -> Vector3<f64> {
+        if recursion_depth < 1.0 {
+            return Vector3::new(0.0, 0.0, 0.0);
+        }
+        let ray_dir = ray_dir.normalize();
+        let unit_normal = surface_normal.normalize();
+        // normal on the side the ray came from
+        let cos_incident = -ray_dir.dot(&unit_normal);
+        let (unit_normal, cos_incident, eta) = if cos_incident > 0.0 {
+            (unit_normal, cos_incident, 1.0 / self.ior)
+        } else {
+            (-unit_normal, -cos_incident, self.ior)
+        };
+
+        let reflected_dir = ray_dir + 2.0 * cos_incident * unit_normal;
+        let reflected_ray = Ray::secondary(intersection_pos, Unit::new_normalize(reflected_dir));
+        let sin2_transmitted = eta * eta * (1.0 - cos_incident * cos_incident);
+        if sin2_transmitted >= 1.0 {
+            // total internal reflection
+            return world.appearance(reflected_ray, recursion_depth - 1.0);
+        }
+
+        // Snell's law
+        let cos_transmitted = (1.0 - sin2_transmitted).sqrt();
+        let refracted_dir = eta * ray_dir + (eta * cos_incident - cos_transmitted) * unit_normal;
+        let refracted_ray = Ray::secondary(intersection_pos, Unit::new_normalize(refracted_dir));
+        let reflectance = self.fresnel.reflectance(cos_incident, cos_transmitted, eta);
+        reflectance * world.appearance(reflected_ray, recursion_depth - 1.0)
+            + (1.0 - reflectance)
+                * world
+                    .appearance(refracted_ray, recursion_depth - 1.0)
+                    .component_mul(&self.color)
+    }
+
+    fn get_albedo_for(&self, _surface_pos: Vector2<f64>) -> Vector3<f64> {
+        self.color
+    }
+}
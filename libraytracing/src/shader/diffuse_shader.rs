@@ -14,10 +14,9 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
-use crate::ray::Ray;
 use crate::shader::Shader;
-use crate::world::World;
-use nalgebra::{Unit, Vector2, Vector3};
+use crate::world::{VisibleLights, World};
+use nalgebra::{Vector2, Vector3};
 
 pub struct DiffuseShader {
     pub color: Vector3<f64>,
@@ -32,29 +31,26 @@ impl DiffuseShader {
 impl Shader for DiffuseShader {
     fn get_appearance_for(
         &self,
-        intersection_pos: Vector3<f64>,
+        _intersection_pos: Vector3<f64>,
         _ray_dir: Vector3<f64>,
-        _surface_normal: Vector3<f64>,
-        world: &World,
+        surface_normal: Vector3<f64>,
+        _world: &World,
+        lights: &VisibleLights,
         _surface_pos: Vector2<f64>,
         _recursion_depth: f64,
     ) -> Vector3<f64> {
         let mut i_diffuse = Vector3::new(0.0, 0.0, 0.0);
 
-        for light in &world.lights {
-            let shade_ray = Ray {
-                dir: Unit::new_normalize(intersection_pos - light.pos),
-                start: light.pos,
-            };
-
-            if let Some(shade_intersection) = world.next_intersection(&shade_ray) {
-                if (shade_intersection.pos - intersection_pos).norm() < 0.001 {
-                    let l_m = shade_ray.dir.normalize();
-                    let n_hat = shade_intersection.normal_at_surface.normalize();
-                    i_diffuse += 2.0 * (-l_m.dot(&n_hat) * self.color).component_mul(&light.color);
-                }
-            }
+        let n_hat = surface_normal.normalize();
+        for light in lights.get().iter() {
+            // flat surfaces are lit from both sides
+            i_diffuse +=
+                2.0 * (light.dir.dot(&n_hat).abs() * self.color).component_mul(&light.radiance);
         }
         i_diffuse
     }
+
+    fn get_albedo_for(&self, _surface_pos: Vector2<f64>) -> Vector3<f64> {
+        self.color
+    }
 }
@@ -16,11 +16,15 @@
 
 use crate::ray::Ray;
 use crate::shader::Shader;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use nalgebra::{Unit, Vector2, Vector3};
 
-pub struct MirrorShader {
-    pub initial_step: f64,
+pub struct MirrorShader;
+
+impl MirrorShader {
+    pub fn new() -> Box<Shader> {
+        Box::new(MirrorShader)
+    }
 }
 
 impl Shader for MirrorShader {
@@ -30,6 +34,7 @@ impl Shader for MirrorShader {
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        _lights: &VisibleLights,
         _surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Vector3<f64> {
@@ -39,10 +44,12 @@ impl Shader for MirrorShader {
         let unit_normal = surface_normal.normalize();
         let othogonal_part = unit_normal.dot(&-ray_dir) * unit_normal;
         let mirror_ray_dir = (2.0 * othogonal_part + ray_dir).normalize();
-        let mirror_ray = Ray {
-            start: intersection_pos + mirror_ray_dir * self.initial_step,
-            dir: Unit::new_normalize(mirror_ray_dir),
-        };
+        let mirror_ray = Ray::secondary(intersection_pos, Unit::new_normalize(mirror_ray_dir));
         world.appearance(mirror_ray, recursion_depth - 1.0)
     }
+
+    fn get_albedo_for(&self, _surface_pos: Vector2<f64>) -> Vector3<f64> {
+        // reflects all light
+        Vector3::new(1.0, 1.0, 1.0)
+    }
 }
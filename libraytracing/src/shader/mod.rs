@@ -20,27 +20,33 @@ use crate::shader::chess_shader::*;
 use crate::shader::diffuse_shader::*;
 use crate::shader::monochrome_shader::*;
 use crate::shader::specular_shader::*;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use image::Rgba;
 use nalgebra::{Vector2, Vector3};
 
-pub trait Shader {
+pub trait Shader: Send + Sync {
+    /// Light leaving the surface towards the ray, lights are the light sources that reach
+    /// intersection_pos, shared by all shaders of the hit
+    #[allow(clippy::too_many_arguments)]
     fn get_appearance_for(
         &self,
         intersection_pos: Vector3<f64>,
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        lights: &VisibleLights,
         surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Vector3<f64>;
     //default implementation to get a rgb<u8> (instead of a vector<f64>)
+    #[allow(clippy::too_many_arguments)]
     fn get_color_for(
         &self,
         intersection_pos: Vector3<f64>,
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        lights: &VisibleLights,
         surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Rgba<u8> {
@@ -49,12 +55,17 @@ pub trait Shader {
             ray_dir,
             surface_normal,
             world,
+            lights,
             surface_pos,
             recursion_depth,
         );
         let u8val = val.map(|x| (x * 255.0).min(255.0).max(0.0) as u8);
         vector2color(&u8val)
     }
+    /// Color of the surface without any lighting, for the albedo pass
+    fn get_albedo_for(&self, _surface_pos: Vector2<f64>) -> Vector3<f64> {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
 }
 
 pub fn get_phong(color: Vector3<f64>) -> Box<Shader> {
@@ -81,8 +92,11 @@ pub fn get_bw_chess() -> Box<Shader> {
 pub mod additive_shader;
 pub mod ambient_shader;
 pub mod chess_shader;
+pub mod dielectric_shader;
 pub mod diffuse_shader;
+pub mod emissive_shader;
 pub mod mirror_shader;
 pub mod monochrome_shader;
 pub mod multiplicative_shader;
 pub mod specular_shader;
+pub mod texture_shader;
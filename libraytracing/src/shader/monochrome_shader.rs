@@ -15,7 +15,7 @@
  * file for more details. **/
 
 use crate::shader::Shader;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use na::{Vector2, Vector3};
 
 pub struct MonochromeShader {
@@ -29,9 +29,14 @@ impl Shader for MonochromeShader {
         _ray_dir: Vector3<f64>,
         _surface_normal: Vector3<f64>,
         _world: &World,
+        _lights: &VisibleLights,
         _surface_pos: Vector2<f64>,
         _recursion_depth: f64,
     ) -> Vector3<f64> {
         self.color
     }
+
+    fn get_albedo_for(&self, _surface_pos: Vector2<f64>) -> Vector3<f64> {
+        self.color
+    }
 }
@@ -15,7 +15,7 @@
  * file for more details. **/
 
 use crate::shader::Shader;
-use crate::world::World;
+use crate::world::{VisibleLights, World};
 use nalgebra::{Vector2, Vector3};
 use std::ops::Mul;
 
@@ -32,6 +32,7 @@ impl Shader for MultiplicativeShader<f64> {
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        lights: &VisibleLights,
         surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Vector3<f64> {
@@ -41,10 +42,16 @@ impl Shader for MultiplicativeShader<f64> {
                 ray_dir,
                 surface_normal,
                 world,
+                lights,
                 surface_pos,
                 recursion_depth,
             )
     }
+
+    /// Scaling weighs the light, the surface keeps its color
+    fn get_albedo_for(&self, surface_pos: Vector2<f64>) -> Vector3<f64> {
+        self.shader.get_albedo_for(surface_pos)
+    }
 }
 // shader * shader (dynamic dispatch)
 impl Shader for MultiplicativeShader<Box<Shader>> {
@@ -54,6 +61,7 @@ impl Shader for MultiplicativeShader<Box<Shader>> {
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        lights: &VisibleLights,
         surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Vector3<f64> {
@@ -62,6 +70,7 @@ impl Shader for MultiplicativeShader<Box<Shader>> {
             ray_dir,
             surface_normal,
             world,
+            lights,
             surface_pos,
             recursion_depth,
         );
@@ -70,11 +79,17 @@ impl Shader for MultiplicativeShader<Box<Shader>> {
             ray_dir,
             surface_normal,
             world,
+            lights,
             surface_pos,
             recursion_depth,
         );
         l.component_mul(&r)
     }
+
+    fn get_albedo_for(&self, surface_pos: Vector2<f64>) -> Vector3<f64> {
+        let l = self.alpha.get_albedo_for(surface_pos);
+        l.component_mul(&self.shader.get_albedo_for(surface_pos))
+    }
 }
 // shader * shader
 impl<T: Shader + 'static> Shader for MultiplicativeShader<Box<T>> {
@@ -84,6 +99,7 @@ impl<T: Shader + 'static> Shader for MultiplicativeShader<Box<T>> {
         ray_dir: Vector3<f64>,
         surface_normal: Vector3<f64>,
         world: &World,
+        lights: &VisibleLights,
         surface_pos: Vector2<f64>,
         recursion_depth: f64,
     ) -> Vector3<f64> {
@@ -92,6 +108,7 @@ impl<T: Shader + 'static> Shader for MultiplicativeShader<Box<T>> {
             ray_dir,
             surface_normal,
             world,
+            lights,
             surface_pos,
             recursion_depth,
         );
@@ -100,11 +117,17 @@ impl<T: Shader + 'static> Shader for MultiplicativeShader<Box<T>> {
             ray_dir,
             surface_normal,
             world,
+            lights,
             surface_pos,
             recursion_depth,
         );
         l.component_mul(&r)
     }
+
+    fn get_albedo_for(&self, surface_pos: Vector2<f64>) -> Vector3<f64> {
+        let l = self.alpha.get_albedo_for(surface_pos);
+        l.component_mul(&self.shader.get_albedo_for(surface_pos))
+    }
 }
 
 // scalar * shader
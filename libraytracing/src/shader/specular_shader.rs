@@ -14,10 +14,9 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
-use crate::ray::Ray;
 use crate::shader::Shader;
-use crate::world::World;
-use nalgebra::{Unit, Vector2, Vector3};
+use crate::world::{VisibleLights, World};
+use nalgebra::{Vector2, Vector3};
 
 pub struct SpecularShader {
     pub alpha: f64,
@@ -32,32 +31,24 @@ impl SpecularShader {
 impl Shader for SpecularShader {
     fn get_appearance_for(
         &self,
-        intersection_pos: Vector3<f64>,
+        _intersection_pos: Vector3<f64>,
         ray_dir: Vector3<f64>,
-        _surface_normal: Vector3<f64>,
-        world: &World,
+        surface_normal: Vector3<f64>,
+        _world: &World,
+        lights: &VisibleLights,
         _surface_pos: Vector2<f64>,
         _recursion_depth: f64,
     ) -> Vector3<f64> {
         let mut i_specular = Vector3::new(0.0, 0.0, 0.0);
 
-        for light in &world.lights {
-            let shade_ray = Ray {
-                dir: Unit::new_normalize(intersection_pos - light.pos),
-                start: light.pos,
-            };
-
-            if let Some(shade_intersection) = world.next_intersection(&shade_ray) {
-                if (shade_intersection.pos - intersection_pos).norm() < 0.1 {
-                    let l_m = -shade_ray.dir.normalize();
-                    let n_hat = shade_intersection.normal_at_surface.normalize();
-                    let r_hat = (2.0 * l_m.dot(&n_hat) * n_hat - l_m).normalize();
-                    let v_hat = -ray_dir.normalize();
-                    //TODO: put shininess(Reflektionsfaktor) in intersection
-                    let rv = r_hat.dot(&v_hat);
-                    i_specular += (if rv > 0.0 { rv } else { 0.0 }).powf(self.alpha) * light.color;
-                }
-            }
+        let n_hat = surface_normal.normalize();
+        let v_hat = -ray_dir.normalize();
+        for light in lights.get().iter() {
+            let l_m = light.dir;
+            let r_hat = (2.0 * l_m.dot(&n_hat) * n_hat - l_m).normalize();
+            //TODO: put shininess(Reflektionsfaktor) in intersection
+            let rv = r_hat.dot(&v_hat);
+            i_specular += (if rv > 0.0 { rv } else { 0.0 }).powf(self.alpha) * light.radiance;
         }
         i_specular
     }
@@ -0,0 +1,232 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::output::{pfm, radiance};
+use crate::shader::Shader;
+use crate::tone_mapping::srgb_to_linear;
+use crate::world::{VisibleLights, World};
+use nalgebra::{Vector2, Vector3};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How a texture is read between its texels
+#[derive(Clone, Copy)]
+pub enum TextureFilter {
+    /// Color of the nearest texel
+    Nearest,
+    /// Weighted mean of the four nearest texels
+    Bilinear,
+}
+
+/// What a texture looks like outside of [0, 1] x [0, 1]
+#[derive(Clone, Copy)]
+pub enum WrapMode {
+    /// The texture is tiled
+    Repeat,
+    /// The texels at the border are extended
+    Clamp,
+}
+
+impl FromStr for TextureFilter {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        match name {
+            "nearest" => Ok(TextureFilter::Nearest),
+            "bilinear" => Ok(TextureFilter::Bilinear),
+            _ => Err(Error::from(format!("Unknown texture filter {}", name))),
+        }
+    }
+}
+
+impl FromStr for WrapMode {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self, Error> {
+        match name {
+            "repeat" => Ok(WrapMode::Repeat),
+            "clamp" => Ok(WrapMode::Clamp),
+            _ => Err(Error::from(format!("Unknown wrap mode {}", name))),
+        }
+    }
+}
+
+/// Image with linear colors, row by row with the top row first
+pub struct Texture {
+    width: usize,
+    height: usize,
+    texels: Vec<Vector3<f64>>,
+}
+
+impl Texture {
+    /// Texture from linear colors, row by row with the top row first. Textures can't be empty.
+    pub fn new(width: usize, height: usize, texels: Vec<Vector3<f64>>) -> Result<Self, Error> {
+        if width == 0 || height == 0 {
+            return Err(Error::from("the image is empty"));
+        }
+        if texels.len() != width * height {
+            return Err(Error::from(format!(
+                "{} texels don't make a {}x{} image",
+                texels.len(),
+                width,
+                height
+            )));
+        }
+        Ok(Texture {
+            width,
+            height,
+            texels,
+        })
+    }
+
+    /// Load an image file, its colors are converted from sRGB to linear. Radiance (hdr) and
+    /// Portable Float Map (pfm) files already have linear colors and keep values above 1.
+    pub fn open(path: &str) -> Result<Self, Error> {
+        let (width, height, texels) = Self::load(path)?;
+        Texture::new(width, height, texels)
+            .map_err(|error| Error::from(format!("Could not load texture {}: {}", path, error)))
+    }
+
+    /// Size and linear colors of an image file
+    fn load(path: &str) -> Result<(usize, usize, Vec<Vector3<f64>>), Error> {
+        let extension = Path::new(path)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        let read_float = match extension.as_ref().map(String::as_str) {
+            Some("hdr") => radiance::read,
+            Some("pfm") => pfm::read,
+            _ => return Self::load_ldr(path),
+        };
+        let (width, height, pixels) = File::open(path)
+            .and_then(|file| read_float(&mut BufReader::new(file)))
+            .map_err(|error| Error::from(format!("Could not load texture {}: {}", path, error)))?;
+        let texels = pixels.iter().map(|pixel| pixel.map(f64::from)).collect();
+        Ok((width, height, texels))
+    }
+
+    fn load_ldr(path: &str) -> Result<(usize, usize, Vec<Vector3<f64>>), Error> {
+        let image = image::open(path)
+            .map_err(|error| Error::from(format!("Could not load texture {}: {}", path, error)))?
+            .to_rgb();
+        let texels = image
+            .pixels()
+            .map(|pixel| {
+                Vector3::new(
+                    srgb_to_linear(f64::from(pixel[0]) / 255.0),
+                    srgb_to_linear(f64::from(pixel[1]) / 255.0),
+                    srgb_to_linear(f64::from(pixel[2]) / 255.0),
+                )
+            })
+            .collect();
+        Ok((image.width() as usize, image.height() as usize, texels))
+    }
+
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+
+    /// Color of the texel in column x and row y, counted from the top
+    pub fn get_texel(&self, x: usize, y: usize) -> Vector3<f64> {
+        self.texels[y * self.width + x]
+    }
+
+    /// Color at the texture coordinates uv, v goes from the bottom (0) to the top (1) row
+    pub fn sample(&self, uv: Vector2<f64>, filter: TextureFilter, wrap: WrapMode) -> Vector3<f64> {
+        let x = uv.x * self.width as f64;
+        let y = (1.0 - uv.y) * self.height as f64;
+        match filter {
+            TextureFilter::Nearest => self.texel(x.floor() as i64, y.floor() as i64, wrap),
+            TextureFilter::Bilinear => {
+                // texel centers are at half-integer positions
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = (1.0 - fx) * self.texel(x0, y0, wrap) + fx * self.texel(x0 + 1, y0, wrap);
+                let bottom =
+                    (1.0 - fx) * self.texel(x0, y0 + 1, wrap) + fx * self.texel(x0 + 1, y0 + 1, wrap);
+                (1.0 - fy) * top + fy * bottom
+            }
+        }
+    }
+
+    fn texel(&self, x: i64, y: i64, wrap: WrapMode) -> Vector3<f64> {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let (x, y) = match wrap {
+            WrapMode::Repeat => (x.rem_euclid(width), y.rem_euclid(height)),
+            WrapMode::Clamp => (x.max(0).min(width - 1), y.max(0).min(height - 1)),
+        };
+        self.texels[y as usize * self.width + x as usize]
+    }
+}
+
+/// Color from an image at the texture coordinates of the surface, without lighting.
+/// Multiply it with a lit shader to get a textured surface.
+pub struct TextureShader {
+    pub texture: Arc<Texture>,
+    pub filter: TextureFilter,
+    pub wrap: WrapMode,
+}
+
+impl TextureShader {
+    pub fn new(texture: Arc<Texture>, filter: TextureFilter, wrap: WrapMode) -> Box<Shader> {
+        Box::new(TextureShader {
+            texture,
+            filter,
+            wrap,
+        })
+    }
+}
+
+impl Shader for TextureShader {
+    fn get_appearance_for(
+        &self,
+        _intersection_pos: Vector3<f64>,
+        _ray_dir: Vector3<f64>,
+        _surface_normal: Vector3<f64>,
+        _world: &World,
+        _lights: &VisibleLights,
+        surface_pos: Vector2<f64>,
+        _recursion_depth: f64,
+    ) -> Vector3<f64> {
+        self.texture.sample(surface_pos, self.filter, self.wrap)
+    }
+
+    fn get_albedo_for(&self, surface_pos: Vector2<f64>) -> Vector3<f64> {
+        self.texture.sample(surface_pos, self.filter, self.wrap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_empty_or_short_texels() {
+        assert!(Texture::new(0, 4, Vec::new()).is_err());
+        assert!(Texture::new(2, 2, vec![Vector3::zeros(); 3]).is_err());
+        let texture = Texture::new(2, 1, vec![Vector3::zeros(); 2]).unwrap();
+        assert_eq!((texture.get_width(), texture.get_height()), (2, 1));
+    }
+}
@@ -16,144 +16,477 @@
 
 use crate::intersection::Intersection;
 use crate::ray::Ray;
-use crate::storage::primitive_storage::PrimitiveStorage;
+use crate::storage::collector::Ids;
 use crate::storage::Bounded;
 use crate::world::Interceptable;
 use nalgebra::Vector3;
-use crate::helpers::*;
 use std::f64;
 
-pub struct BVStorage {
-    left: Box<Interceptable>,
-    right: Box<Interceptable>,
+/// Deepest level of the hierarchy, deeper nodes become leaves so that the traversal stack of
+/// 64 entries can't overflow
+const MAX_DEPTH: usize = 60;
+
+/// How the bounding volume hierarchy is split into nodes
+#[derive(Clone, Copy)]
+pub enum Builder {
+    /// Split at the midpoint of the element minima along the widest axis
+    Midpoint,
+    /// Split where the surface area heuristic estimates the cheapest traversal
+    Sah(SahParameters),
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Builder::Sah(SahParameters::default())
+    }
+}
+
+/// Constants of the surface area heuristic: the cost of a split is
+/// traversal_cost + (area_left * n_left + area_right * n_right) / area * intersection_cost
+#[derive(Clone, Copy)]
+pub struct SahParameters {
+    /// Number of buckets the centroids are sorted into along each axis
+    pub bins: usize,
+    /// Nodes with more elements are always split, if the centroids allow it
+    pub max_leaf_size: usize,
+    pub traversal_cost: f64,
+    pub intersection_cost: f64,
+}
+
+impl Default for SahParameters {
+    fn default() -> Self {
+        SahParameters {
+            bins: 16,
+            max_leaf_size: 4,
+            traversal_cost: 1.0,
+            intersection_cost: 1.0,
+        }
+    }
+}
+
+/// The bounds of a primitive, so they are computed only once while building
+struct BuildElement {
+    index: usize,
     min: Vector3<f64>,
     max: Vector3<f64>,
+    centroid: Vector3<f64>,
 }
 
-impl BVStorage {
-	fn from(elements: Vec<Box<Bounded>>, min: Vector3<f64>, max:Vector3<f64>) -> Self {
-		return BVStorage {
-				min,
-				max,
-				left: Box::new(PrimitiveStorage { elements: bounded2interceptable(elements)}),
-				right: Box::new(PrimitiveStorage { elements: Vec::new()}),
-			}
-	}
-	
-    pub fn new(elements: Vec<Box<Bounded>>) -> Self {
-        let box_min = pointwise_min_list(elements.iter().map(|v| v.get_min()).collect());
-        let box_max = pointwise_max_list(elements.iter().map(|v| v.get_max()).collect());
+#[derive(Clone, Copy)]
+struct Bin {
+    count: usize,
+    min: Vector3<f64>,
+    max: Vector3<f64>,
+}
+
+impl Bin {
+    fn empty() -> Self {
+        Bin {
+            count: 0,
+            min: Vector3::repeat(f64::INFINITY),
+            max: Vector3::repeat(f64::NEG_INFINITY),
+        }
+    }
 
-        let mins_max = pointwise_max_list(elements.iter().map(|v| v.get_min()).collect());
+    fn add(&mut self, other: &Bin) {
+        self.count += other.count;
+        self.min = pointwise_min(self.min, other.min);
+        self.max = pointwise_max(self.max, other.max);
+    }
+}
 
-        let split_dimension = (mins_max - box_min).iamax();
-        let split_point = (mins_max[split_dimension] + box_min[split_dimension]) / 2.0;
+/// Bounding volume hierarchy over primitives that are stored elsewhere and referred to by
+/// index, flattened into a Vec of nodes in depth-first order
+#[derive(Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+}
 
-        let mut lower_elements = Vec::new();
-        let mut upper_elements = Vec::new();
-        for element in elements {
-            if element.get_min()[split_dimension] < split_point {
-                lower_elements.push(element);
-            } else {
-                upper_elements.push(element);
+/// The first child of an inner node directly follows it in the node list
+#[derive(Clone)]
+struct Node {
+    min: Vector3<f64>,
+    max: Vector3<f64>,
+    content: NodeContent,
+}
+
+#[derive(Clone)]
+enum NodeContent {
+    /// Primitives first..first + count
+    Leaf { first: u32, count: u32 },
+    /// The primitives were split along axis, the second child is the upper side
+    Inner { second_child: u32, axis: u8 },
+}
+
+/// Hierarchy as produced by the builders, before it is flattened
+enum BuildNode {
+    Leaf(Vec<usize>),
+    Inner {
+        axis: usize,
+        lower: Box<BuildNode>,
+        upper: Box<BuildNode>,
+    },
+}
+
+impl Bvh {
+    /// Build the hierarchy over primitives with the given (min, max) bounds. Also returns
+    /// the order the primitives have to be stored in, the leaves refer to ranges of it.
+    pub fn build(bounds: &[(Vector3<f64>, Vector3<f64>)], builder: &Builder) -> (Self, Vec<usize>) {
+        let elements = bounds
+            .iter()
+            .enumerate()
+            .map(|(index, &(min, max))| BuildElement {
+                index,
+                min,
+                max,
+                centroid: (min + max) / 2.0,
+            })
+            .collect();
+        let root = match builder {
+            Builder::Midpoint => midpoint_node(elements, 0),
+            Builder::Sah(parameters) => sah_node(elements, parameters, 0),
+        };
+        let mut bvh = Bvh { nodes: Vec::new() };
+        let mut order = Vec::with_capacity(bounds.len());
+        bvh.push_node(root, bounds, &mut order);
+        (bvh, order)
+    }
+
+    /// Appends the node and its children in depth-first order, returns the bounds of the node
+    fn push_node(
+        &mut self,
+        node: BuildNode,
+        bounds: &[(Vector3<f64>, Vector3<f64>)],
+        order: &mut Vec<usize>,
+    ) -> (Vector3<f64>, Vector3<f64>) {
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            min: Vector3::zeros(),
+            max: Vector3::zeros(),
+            content: NodeContent::Leaf { first: 0, count: 0 },
+        });
+        let (min, max, content) = match node {
+            BuildNode::Leaf(indices) => {
+                let min = pointwise_min_list(indices.iter().map(|&i| bounds[i].0).collect());
+                let max = pointwise_max_list(indices.iter().map(|&i| bounds[i].1).collect());
+                let first = order.len() as u32;
+                let count = indices.len() as u32;
+                order.extend(indices);
+                (min, max, NodeContent::Leaf { first, count })
+            }
+            BuildNode::Inner { axis, lower, upper } => {
+                let (lower_min, lower_max) = self.push_node(*lower, bounds, order);
+                let second_child = self.nodes.len() as u32;
+                let (upper_min, upper_max) = self.push_node(*upper, bounds, order);
+                (
+                    pointwise_min(lower_min, upper_min),
+                    pointwise_max(lower_max, upper_max),
+                    NodeContent::Inner {
+                        second_child,
+                        axis: axis as u8,
+                    },
+                )
+            }
+        };
+        self.nodes[index] = Node { min, max, content };
+        (min, max)
+    }
+
+    pub fn get_min(&self) -> Vector3<f64> {
+        self.nodes[0].min
+    }
+
+    pub fn get_max(&self) -> Vector3<f64> {
+        self.nodes[0].max
+    }
+
+    /// Nearest hit, intercept is called with the position of a primitive in the order
+    /// returned by build. The ray it gets is shortened to the nearest hit found so far.
+    pub fn intercept<'a, F>(&self, ray: &Ray, mut intercept: F) -> Option<(f64, Intersection<'a>)>
+    where
+        F: FnMut(usize, &Ray) -> Option<(f64, Intersection<'a>)>,
+    {
+        let speed = ray.dir.map(|d| if d == 0.0 { f64::MIN_POSITIVE } else { d });
+        let inverse_speed = speed.map(|d| 1.0 / d);
+
+        // every hit shortens the ray, so boxes behind it are skipped
+        let mut ray = *ray;
+        let mut interception = None;
+        let mut stack = [0u32; 64];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let index = stack[stack_size] as usize;
+            let node = &self.nodes[index];
+            if !hits_box(node, &ray, &inverse_speed) {
+                continue;
+            }
+            match node.content {
+                NodeContent::Leaf { first, count } => {
+                    for i in first as usize..(first + count) as usize {
+                        if let Some((distance, intercept)) = intercept(i, &ray) {
+                            ray.t_max = distance;
+                            interception = Some((distance, intercept));
+                        }
+                    }
+                }
+                NodeContent::Inner { second_child, axis } => {
+                    // visit the nearer child first, it is on top of the stack
+                    let first_child = index as u32 + 1;
+                    let (near, far) = if speed[axis as usize] < 0.0 {
+                        (second_child, first_child)
+                    } else {
+                        (first_child, second_child)
+                    };
+                    stack[stack_size] = far;
+                    stack[stack_size + 1] = near;
+                    stack_size += 2;
+                }
             }
         }
+        interception
+    }
 
-		if lower_elements.len() == 0 {
-			return Self::from(upper_elements, box_min, box_max);
-		}
-		if upper_elements.len() == 0 {
-			return Self::from(lower_elements, box_min, box_max);
-		}
+    /// Whether occluded is true for any primitive whose boxes the ray passes through
+    pub fn occluded<F>(&self, ray: &Ray, mut occluded: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let speed = ray.dir.map(|d| if d == 0.0 { f64::MIN_POSITIVE } else { d });
+        let inverse_speed = speed.map(|d| 1.0 / d);
 
-        let left: Box<Interceptable> = if lower_elements.len() < 4 {
-            Box::new(PrimitiveStorage { elements: bounded2interceptable(lower_elements)})
-        } else {
-            Box::new(BVStorage::new(lower_elements))
-        };
-        let right: Box<Interceptable> = if upper_elements.len() < 4 {
-            Box::new(PrimitiveStorage { elements: bounded2interceptable(upper_elements)})
+        let mut stack = [0u32; 64];
+        let mut stack_size = 1;
+        while stack_size > 0 {
+            stack_size -= 1;
+            let index = stack[stack_size] as usize;
+            let node = &self.nodes[index];
+            if !hits_box(node, ray, &inverse_speed) {
+                continue;
+            }
+            match node.content {
+                NodeContent::Leaf { first, count } => {
+                    if (first as usize..(first + count) as usize).any(&mut occluded) {
+                        return true;
+                    }
+                }
+                NodeContent::Inner { second_child, .. } => {
+                    stack[stack_size] = second_child;
+                    stack[stack_size + 1] = index as u32 + 1;
+                    stack_size += 2;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Bounding volume hierarchy over boxed elements
+pub struct BVStorage {
+    bvh: Bvh,
+    /// In the order of the leaves
+    elements: Vec<Box<Bounded>>,
+    /// Ids of the elements in the same order, empty if the elements keep their own ids
+    ids: Vec<Ids>,
+}
+
+impl BVStorage {
+    pub fn build(elements: Vec<Box<Bounded>>, builder: &Builder) -> Self {
+        Self::build_identified(elements, Vec::new(), builder)
+    }
+
+    /// Hierarchy over elements that get the ids of the same position in ids
+    pub(crate) fn build_identified(
+        elements: Vec<Box<Bounded>>,
+        ids: Vec<Ids>,
+        builder: &Builder,
+    ) -> Self {
+        let bounds: Vec<_> = elements.iter().map(|e| (e.get_min(), e.get_max())).collect();
+        let (bvh, order) = Bvh::build(&bounds, builder);
+        let mut elements: Vec<Option<Box<Bounded>>> = elements.into_iter().map(Some).collect();
+        let elements = order.iter().map(|&i| elements[i].take().unwrap()).collect();
+        let ids = if ids.is_empty() {
+            ids
         } else {
-            Box::new(BVStorage::new(upper_elements))
+            order.iter().map(|&i| ids[i]).collect()
         };
+        BVStorage { bvh, elements, ids }
+    }
 
-        BVStorage {
-            min: box_min,
-            max: box_max,
-            left,
-            right,
-        }
+    /// Binned surface area heuristic builder
+    pub fn new_sah(elements: Vec<Box<Bounded>>, parameters: &SahParameters) -> Self {
+        Self::build(elements, &Builder::Sah(*parameters))
+    }
+
+    /// Builder that splits at the midpoint of the element minima
+    pub fn new(elements: Vec<Box<Bounded>>) -> Self {
+        Self::build(elements, &Builder::Midpoint)
     }
 }
 
 impl Interceptable for BVStorage {
     fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
-        let xspeed = if ray.dir.x == 0.0 {
-            f64::MIN_POSITIVE
-        } else {
-            ray.dir.x
-        };
-        let yspeed = if ray.dir.y == 0.0 {
-            f64::MIN_POSITIVE
-        } else {
-            ray.dir.y
-        };
-        let zspeed = if ray.dir.z == 0.0 {
-            f64::MIN_POSITIVE
-        } else {
-            ray.dir.z
-        };
-        let (txm, tx_m) = if ray.dir.x > 0.0 {
-            (
-                (self.min.x - ray.start.x) / xspeed,
-                (self.max.x - ray.start.x) / xspeed,
-            )
+        if self.ids.is_empty() {
+            self.bvh.intercept(ray, |i, ray| self.elements[i].intercept(ray))
         } else {
-            (
-                (self.max.x - ray.start.x) / xspeed,
-                (self.min.x - ray.start.x) / xspeed,
-            )
-        };
+            self.bvh.intercept(ray, |i, ray| {
+                self.elements[i]
+                    .intercept(ray)
+                    .map(|hit| self.ids[i].apply(hit))
+            })
+        }
+    }
 
-        let (tym, ty_m) = if ray.dir.y > 0.0 {
-            (
-                (self.min.y - ray.start.y) / yspeed,
-                (self.max.y - ray.start.y) / yspeed,
-            )
-        } else {
-            (
-                (self.max.y - ray.start.y) / yspeed,
-                (self.min.y - ray.start.y) / yspeed,
-            )
-        };
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.bvh.occluded(ray, |i| self.elements[i].occluded(ray))
+    }
+}
 
-        let (tzm, tz_m) = if ray.dir.z > 0.0 {
-            (
-                (self.min.z - ray.start.z) / zspeed,
-                (self.max.z - ray.start.z) / zspeed,
-            )
-        } else {
-            (
-                (self.max.z - ray.start.z) / zspeed,
-                (self.min.z - ray.start.z) / zspeed,
-            )
-        };
+impl Bounded for BVStorage {
+    fn get_min(&self) -> Vector3<f64> {
+        self.bvh.get_min()
+    }
+
+    fn get_max(&self) -> Vector3<f64> {
+        self.bvh.get_max()
+    }
+}
+
+fn sah_node(elements: Vec<BuildElement>, parameters: &SahParameters, depth: usize) -> BuildNode {
+    if depth >= MAX_DEPTH {
+        return leaf(elements);
+    }
+    let box_min = pointwise_min_list(elements.iter().map(|e| e.min).collect());
+    let box_max = pointwise_max_list(elements.iter().map(|e| e.max).collect());
+    let centroid_min = pointwise_min_list(elements.iter().map(|e| e.centroid).collect());
+    let centroid_max = pointwise_max_list(elements.iter().map(|e| e.centroid).collect());
+    let extent = centroid_max - centroid_min;
+    let bins = parameters.bins.max(2);
+    let bin_of = |centroid: &Vector3<f64>, axis: usize| {
+        let relative = (centroid[axis] - centroid_min[axis]) / extent[axis];
+        ((relative * bins as f64) as usize).min(bins - 1)
+    };
 
-        if txm.max(tym).max(tzm) > tx_m.min(ty_m).min(tz_m) {
-            return None;
+    // cheapest split over all axes, as (cost, axis, number of bins on the lower side)
+    let mut best: Option<(f64, usize, usize)> = None;
+    for axis in 0..3 {
+        if !extent[axis].is_finite() || extent[axis] <= 0.0 {
+            continue;
         }
-        return match (self.left.intercept(ray), self.right.intercept(ray)) {
-            (None, x) => x,
-            (x, None) => x,
-            (Some((dist1, int1)), Some((dist2, int2))) => {
-                if dist1 < dist2 {
-                    Some((dist1, int1))
+        let mut axis_bins = vec![Bin::empty(); bins];
+        for e in &elements {
+            let bin = &mut axis_bins[bin_of(&e.centroid, axis)];
+            bin.add(&Bin {
+                count: 1,
+                min: e.min,
+                max: e.max,
+            });
+        }
+        // sweep from the right to get the bounds of all upper sides
+        let mut upper = vec![Bin::empty(); bins];
+        let mut accumulated = Bin::empty();
+        for i in (1..bins).rev() {
+            accumulated.add(&axis_bins[i]);
+            upper[i] = accumulated;
+        }
+        let mut lower = Bin::empty();
+        for i in 1..bins {
+            lower.add(&axis_bins[i - 1]);
+            if lower.count == 0 || upper[i].count == 0 {
+                continue;
+            }
+            let cost = lower.count as f64 * surface_area(lower.min, lower.max)
+                + upper[i].count as f64 * surface_area(upper[i].min, upper[i].max);
+            if best.map_or(true, |(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, i));
+            }
+        }
+    }
+
+    let leaf_cost = elements.len() as f64 * parameters.intersection_cost;
+    let (axis, split) = match best {
+        Some((cost, axis, split)) => {
+            let area = surface_area(box_min, box_max);
+            let split_cost = parameters.traversal_cost
+                + if area > 0.0 {
+                    cost / area * parameters.intersection_cost
                 } else {
-                    Some((dist2, int2))
-                }
+                    leaf_cost
+                };
+            if elements.len() <= parameters.max_leaf_size && split_cost >= leaf_cost {
+                return leaf(elements);
             }
-        };
+            (axis, split)
+        }
+        None => return leaf(elements),
+    };
+
+    let (lower_elements, upper_elements): (Vec<_>, Vec<_>) = elements
+        .into_iter()
+        .partition(|e| bin_of(&e.centroid, axis) < split);
+
+    BuildNode::Inner {
+        axis,
+        lower: Box::new(sah_node(lower_elements, parameters, depth + 1)),
+        upper: Box::new(sah_node(upper_elements, parameters, depth + 1)),
+    }
+}
+
+fn leaf(elements: Vec<BuildElement>) -> BuildNode {
+    BuildNode::Leaf(elements.into_iter().map(|e| e.index).collect())
+}
+
+fn midpoint_node(elements: Vec<BuildElement>, depth: usize) -> BuildNode {
+    if depth >= MAX_DEPTH {
+        return leaf(elements);
+    }
+    let box_min = pointwise_min_list(elements.iter().map(|e| e.min).collect());
+    let mins_max = pointwise_max_list(elements.iter().map(|e| e.min).collect());
+
+    let split_dimension = (mins_max - box_min).iamax();
+    let split_point = (mins_max[split_dimension] + box_min[split_dimension]) / 2.0;
+
+    let (lower_elements, upper_elements): (Vec<_>, Vec<_>) = elements
+        .into_iter()
+        .partition(|e| e.min[split_dimension] < split_point);
+
+    if lower_elements.is_empty() {
+        return leaf(upper_elements);
     }
+    if upper_elements.is_empty() {
+        return leaf(lower_elements);
+    }
+
+    let child = |elements: Vec<BuildElement>| {
+        Box::new(if elements.len() < 4 {
+            leaf(elements)
+        } else {
+            midpoint_node(elements, depth + 1)
+        })
+    };
+    BuildNode::Inner {
+        axis: split_dimension,
+        lower: child(lower_elements),
+        upper: child(upper_elements),
+    }
+}
+
+/// Whether the ray passes through the box of the node within its interval
+fn hits_box(node: &Node, ray: &Ray, inverse_speed: &Vector3<f64>) -> bool {
+    let mut entry = ray.t_min;
+    let mut exit = ray.t_max;
+    for axis in 0..3 {
+        let t1 = (node.min[axis] - ray.start[axis]) * inverse_speed[axis];
+        let t2 = (node.max[axis] - ray.start[axis]) * inverse_speed[axis];
+        entry = entry.max(t1.min(t2));
+        exit = exit.min(t1.max(t2));
+    }
+    entry <= exit
+}
+
+fn surface_area(min: Vector3<f64>, max: Vector3<f64>) -> f64 {
+    let d = max - min;
+    2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
 }
 
 fn pointwise_min_list(vectors: Vec<Vector3<f64>>) -> Vector3<f64> {
@@ -14,37 +14,197 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
+use crate::intersection::Intersection;
+use crate::ray::Ray;
 use crate::storage::bv_storage::*;
+use crate::storage::instance_storage::InstanceStorage;
 use crate::storage::primitive_storage::PrimitiveStorage;
 use crate::storage::Bounded;
+use crate::world::instance::Instance;
+use crate::world::triangle_mesh::TriangleMesh;
 use crate::world::Interceptable;
+use na::Vector3;
 
+/// Collects the elements of a scene and numbers them: every element gets its own
+/// primitive id, and the id of the object that was started last with `begin_object`.
+/// Ids start at 1, 0 means no primitive or object.
 pub struct Collector {
-    pub bounded_elements: Vec<Box<Bounded>>,
+    bounded_elements: Vec<Box<Bounded>>,
+    /// Ids of the bounded elements, at the same positions
+    bounded_ids: Vec<Ids>,
     pub elements: Vec<Box<Interceptable + 'static>>,
+    /// Get their own top level hierarchy, see InstanceStorage
+    instances: Vec<Identified<Instance>>,
+    /// Names of the objects, object id i has the name object_names[i - 1]
+    pub object_names: Vec<String>,
+    primitive_count: usize,
 }
 
 impl<'a> Collector {
     pub fn new() -> Self {
         Collector {
             bounded_elements: Vec::new(),
+            bounded_ids: Vec::new(),
             elements: Vec::new(),
+            instances: Vec::new(),
+            object_names: Vec::new(),
+            primitive_count: 0,
         }
     }
+
+    /// All elements added after this belong to a new object with the given name
+    pub fn begin_object(&mut self, name: String) -> () {
+        self.object_names.push(name);
+    }
+
     pub fn add(&mut self, element: Box<Interceptable>) -> () {
-        self.elements.push(element);
+        let ids = self.next_ids();
+        self.primitive_count += 1;
+        self.elements.push(Box::new(Identified { ids, element }));
     }
 
     pub fn add_bounded(&mut self, element: Box<Bounded>) -> () {
+        self.add_bounded_primitives(element, 1);
+    }
+
+    /// Every face of the mesh gets its own primitive id
+    pub fn add_mesh(&mut self, mesh: TriangleMesh) -> () {
+        let count = mesh.face_count();
+        self.add_bounded_primitives(Box::new(mesh), count);
+    }
+
+    /// Add an element that consists of count primitives and gives them the ids 1 to count
+    /// in its intersections, e.g. an instance of the storage of another Collector
+    pub fn add_bounded_primitives(&mut self, element: Box<Bounded>, count: usize) -> () {
+        self.bounded_ids.push(self.next_ids());
         self.bounded_elements.push(element);
+        self.primitive_count += count;
+    }
+
+    /// Add an instance of an element that consists of count primitives, like
+    /// add_bounded_primitives. Instances are kept apart from the other elements in an
+    /// InstanceStorage.
+    pub fn add_instance(&mut self, instance: Instance, count: usize) -> () {
+        let ids = self.next_ids();
+        self.instances.push(Identified {
+            ids,
+            element: instance,
+        });
+        self.primitive_count += count;
+    }
+
+    /// Ids of the element that is added next
+    fn next_ids(&self) -> Ids {
+        Ids {
+            primitive_id: self.primitive_count + 1,
+            object_id: self.object_names.len(),
+        }
+    }
+
+    /// Put the bounded elements in a BVStorage built with the surface area heuristic
+    pub fn into_storage(self) -> Box<Interceptable> {
+        self.into_storage_with(&Builder::default())
+    }
+
+    pub fn into_storage_with(self, builder: &Builder) -> Box<Interceptable> {
+        let (mut elements, instances) = self.into_storages(builder);
+        if !instances.is_empty() {
+            elements.elements.push(Box::new(instances));
+        }
+        Box::new(elements)
     }
 
-    pub fn into_storage(mut self) -> Box<Interceptable> {
-        let bounded_elements = Box::new(BVStorage::new(self.bounded_elements));
+    /// The instances apart from the other elements, so that they can still be moved, e.g. in
+    /// a World made with World::with_instances
+    pub fn into_storages(mut self, builder: &Builder) -> (PrimitiveStorage, InstanceStorage) {
+        let bounded_elements = Box::new(BVStorage::build_identified(
+            self.bounded_elements,
+            self.bounded_ids,
+            builder,
+        ));
         self.elements.push(bounded_elements);
         //self.elements.append(&mut bounded2interceptable(self.bounded_elements));
+        let elements = PrimitiveStorage {
+            elements: self.elements,
+        };
+        (elements, InstanceStorage::build(self.instances, builder))
+    }
+
+    pub fn get_primitive_count(&self) -> usize {
+        self.primitive_count
+    }
+
+    /// Put the bounded elements and the instances in a BVStorage, e.g. to use them in
+    /// instances. The unbounded elements are left out.
+    pub fn into_bounded_storage(mut self) -> BVStorage {
+        self.flatten_instances();
+        BVStorage::build_identified(self.bounded_elements, self.bounded_ids, &Builder::default())
+    }
+
+    /// Put all elements in one PrimitiveStorage, without bounding volumes
+    pub fn into_primitive_storage(mut self) -> Box<Interceptable> {
+        self.flatten_instances();
+        for (element, ids) in self.bounded_elements.into_iter().zip(self.bounded_ids) {
+            self.elements.push(Box::new(Identified { ids, element }));
+        }
         Box::new(PrimitiveStorage {
             elements: self.elements,
         })
     }
+
+    /// Treat the instances like the other bounded elements
+    fn flatten_instances(&mut self) -> () {
+        for instance in self.instances.drain(..) {
+            self.bounded_elements.push(Box::new(instance.element));
+            self.bounded_ids.push(instance.ids);
+        }
+    }
+}
+
+/// Ids the Collector gave an element
+#[derive(Clone, Copy)]
+pub(crate) struct Ids {
+    /// Id of the first primitive of the element
+    pub(crate) primitive_id: usize,
+    pub(crate) object_id: usize,
+}
+
+impl Ids {
+    /// Puts the ids into a hit of the element
+    pub(crate) fn apply<'a>(&self, hit: (f64, Intersection<'a>)) -> (f64, Intersection<'a>) {
+        let (dist, mut intersection) = hit;
+        // elements made of several primitives number them starting at 1
+        intersection.primitive_id = match intersection.primitive_id {
+            0 => self.primitive_id,
+            id => self.primitive_id + id - 1,
+        };
+        intersection.object_id = self.object_id;
+        (dist, intersection)
+    }
+}
+
+/// Puts the ids of an element into its intersections
+#[derive(Clone)]
+pub(crate) struct Identified<T> {
+    pub(crate) ids: Ids,
+    pub(crate) element: T,
+}
+
+impl<T: Interceptable> Interceptable for Identified<T> {
+    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
+        self.element.intercept(ray).map(|hit| self.ids.apply(hit))
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.element.occluded(ray)
+    }
+}
+
+impl<T: Bounded> Bounded for Identified<T> {
+    fn get_min(&self) -> Vector3<f64> {
+        self.element.get_min()
+    }
+    fn get_max(&self) -> Vector3<f64> {
+        self.element.get_max()
+    }
 }
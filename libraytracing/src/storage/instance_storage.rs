@@ -0,0 +1,121 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::intersection::Intersection;
+use crate::ray::Ray;
+use crate::storage::bv_storage::{Builder, Bvh};
+use crate::storage::collector::Identified;
+use crate::storage::Bounded;
+use crate::world::instance::Instance;
+use crate::world::Interceptable;
+use na::{Matrix4, Vector3};
+
+/// Top level of a two-level hierarchy: a bounding volume hierarchy over instances, each of
+/// which brings the hierarchy of its shared object along. Moving instances only rebuilds the
+/// top level, and cloning the storage does not copy the objects.
+#[derive(Clone)]
+pub struct InstanceStorage {
+    /// In the order they were added, set_transform refers to them by this position
+    instances: Vec<Identified<Instance>>,
+    /// Position in instances of each primitive of bvh
+    order: Vec<usize>,
+    bvh: Bvh,
+    builder: Builder,
+}
+
+impl InstanceStorage {
+    pub(crate) fn build(instances: Vec<Identified<Instance>>, builder: &Builder) -> Self {
+        let (bvh, order) = top_level(&instances, builder);
+        InstanceStorage {
+            instances,
+            order,
+            bvh,
+            builder: *builder,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn get_transform(&self, index: usize) -> Option<Matrix4<f64>> {
+        self.instances.get(index).map(|instance| instance.element.get_transform())
+    }
+
+    /// Move one instance, the top level is rebuilt
+    pub fn set_transform(&mut self, index: usize, transform: Matrix4<f64>) -> Result<(), Error> {
+        self.set_transforms(vec![(index, transform)])
+    }
+
+    /// Move several instances, the top level is rebuilt once afterwards
+    pub fn set_transforms<I>(&mut self, transforms: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = (usize, Matrix4<f64>)>,
+    {
+        let result = transforms
+            .into_iter()
+            .map(|(index, transform)| match self.instances.get_mut(index) {
+                Some(instance) => instance.element.set_transform(transform),
+                None => Err(Error::from(format!("There is no instance {}", index))),
+            })
+            .collect();
+        // instances moved before an error are kept moved
+        self.rebuild();
+        result
+    }
+
+    fn rebuild(&mut self) {
+        let (bvh, order) = top_level(&self.instances, &self.builder);
+        self.bvh = bvh;
+        self.order = order;
+    }
+}
+
+/// Hierarchy over the current bounds of the instances
+fn top_level(instances: &[Identified<Instance>], builder: &Builder) -> (Bvh, Vec<usize>) {
+    let bounds: Vec<_> = instances
+        .iter()
+        .map(|instance| (instance.get_min(), instance.get_max()))
+        .collect();
+    Bvh::build(&bounds, builder)
+}
+
+impl Interceptable for InstanceStorage {
+    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
+        self.bvh
+            .intercept(ray, |i, ray| self.instances[self.order[i]].intercept(ray))
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.bvh
+            .occluded(ray, |i| self.instances[self.order[i]].occluded(ray))
+    }
+}
+
+impl Bounded for InstanceStorage {
+    fn get_min(&self) -> Vector3<f64> {
+        self.bvh.get_min()
+    }
+
+    fn get_max(&self) -> Vector3<f64> {
+        self.bvh.get_max()
+    }
+}
@@ -21,6 +21,7 @@ use na::Vector3;
 
 pub mod bv_storage;
 pub mod collector;
+pub mod instance_storage;
 pub mod primitive_storage;
 
 pub trait Bounded: Interceptable {
@@ -28,6 +29,16 @@ pub trait Bounded: Interceptable {
     fn get_max(&self) -> Vector3<f64>;
 }
 
+impl<T: Bounded + ?Sized> Bounded for Box<T> {
+    fn get_min(&self) -> Vector3<f64> {
+        (**self).get_min()
+    }
+
+    fn get_max(&self) -> Vector3<f64> {
+        (**self).get_max()
+    }
+}
+
 impl From<Box<Bounded>> for Box<Interceptable> {
     fn from(element: Box<Bounded>) -> Self {
         Box::new(InterceptFromBound { child: element })
@@ -42,4 +53,8 @@ impl Interceptable for InterceptFromBound {
     fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
         self.child.intercept(ray)
     }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.child.occluded(ray)
+    }
 }
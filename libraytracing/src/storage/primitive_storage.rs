@@ -17,7 +17,6 @@
 use crate::intersection::Intersection;
 use crate::ray::Ray;
 use crate::world::Interceptable;
-use std::f64;
 
 pub struct PrimitiveStorage {
     pub elements: Vec<Box<Interceptable>>,
@@ -25,20 +24,19 @@ pub struct PrimitiveStorage {
 
 impl Interceptable for PrimitiveStorage {
     fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
-        let mut max_distance = f64::INFINITY;
+        // every hit shortens the ray, so only nearer hits are found after it
+        let mut ray = *ray;
         let mut interception = None;
         for element in &self.elements {
-            if let Some((distance, intercept)) = element.intercept(ray) {
-                if distance < max_distance {
-                    max_distance = distance;
-                    interception = Some(intercept);
-                }
+            if let Some((distance, intercept)) = element.intercept(&ray) {
+                ray.t_max = distance;
+                interception = Some((distance, intercept));
             }
         }
-        return if let Some(int) = interception {
-            Some((max_distance, int))
-        } else {
-            None
-        };
+        interception
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.elements.iter().any(|element| element.occluded(ray))
     }
 }
@@ -0,0 +1,98 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use na::Vector3;
+
+/// Compresses linear colors with an unbounded range into [0, 1]
+pub trait ToneMapper: Send + Sync {
+    fn map(&self, color: Vector3<f64>) -> Vector3<f64>;
+}
+
+/// Cut everything above 1
+pub struct LinearToneMapper {}
+
+impl LinearToneMapper {
+    pub fn new() -> Box<ToneMapper> {
+        Box::new(LinearToneMapper {})
+    }
+}
+
+impl ToneMapper for LinearToneMapper {
+    fn map(&self, color: Vector3<f64>) -> Vector3<f64> {
+        color.map(|c| c.min(1.0).max(0.0))
+    }
+}
+
+/// Reinhard's operator c (1 + c / white²) / (1 + c) per channel,
+/// values of white and above become 1
+pub struct ReinhardToneMapper {
+    pub white: f64,
+}
+
+impl ReinhardToneMapper {
+    /// The basic operator c / (1 + c), which never reaches 1
+    pub fn new() -> Box<ToneMapper> {
+        Box::new(ReinhardToneMapper {
+            white: std::f64::INFINITY,
+        })
+    }
+}
+
+impl ToneMapper for ReinhardToneMapper {
+    fn map(&self, color: Vector3<f64>) -> Vector3<f64> {
+        let white_squared = self.white * self.white;
+        color.map(|c| {
+            let c = c.max(0.0);
+            (c * (1.0 + c / white_squared) / (1.0 + c)).min(1.0)
+        })
+    }
+}
+
+/// Filmic curve fitted to the ACES reference rendering transform by Krzysztof Narkowicz
+pub struct AcesToneMapper {}
+
+impl AcesToneMapper {
+    pub fn new() -> Box<ToneMapper> {
+        Box::new(AcesToneMapper {})
+    }
+}
+
+impl ToneMapper for AcesToneMapper {
+    fn map(&self, color: Vector3<f64>) -> Vector3<f64> {
+        color.map(|c| {
+            let c = c.max(0.0);
+            ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).min(1.0)
+        })
+    }
+}
+
+/// sRGB transfer function for a linear value in [0, 1]
+pub fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.003_130_8 {
+        12.92 * c
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Inverse of linear_to_srgb, for colors read from images
+pub fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.040_45 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,329 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ *
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::helpers::orthonormal_basis;
+use crate::shader::emissive_shader::EmissiveShader;
+use crate::storage::Bounded;
+use crate::world::light::{Light, LightSample};
+use crate::world::sphere::Sphere;
+use crate::world::triangle::Triangle;
+use na::{Unit, Vector2, Vector3};
+use std::f64;
+
+/// Number of triangles a disk light is shown with
+const DISK_SEGMENTS: usize = 64;
+
+/// Glowing ball that lights everything around it and casts soft shadows
+pub struct SphereLight {
+    pub center: Vector3<f64>,
+    pub radius: f64,
+    pub color: Vector3<f64>,
+    /// Radiance of the surface is color * intensity
+    pub intensity: f64,
+    pub samples: u32,
+    /// Whether camera rays see the sphere
+    pub visible: bool,
+}
+
+impl Light for SphereLight {
+    /// Samples the cone of directions in which the sphere is seen from pos
+    fn sample(&self, pos: Vector3<f64>, u: Vector2<f64>) -> Option<LightSample> {
+        let to_center = self.center - pos;
+        let center_distance = to_center.norm();
+        if center_distance <= self.radius {
+            return None;
+        }
+        let w = to_center / center_distance;
+        let sin_max = self.radius / center_distance;
+        let cos_max = (1.0 - sin_max * sin_max).sqrt();
+        let cos_theta = 1.0 - u.x * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * f64::consts::PI * u.y;
+        let (a, b) = orthonormal_basis(&w);
+        let dir = cos_theta * w + sin_theta * (phi.cos() * a + phi.sin() * b);
+        // nearest intersection of the direction with the sphere
+        let projection = dir.dot(&to_center);
+        let chord = (self.radius * self.radius
+            - (center_distance * center_distance - projection * projection))
+            .max(0.0)
+            .sqrt();
+        let solid_angle = 2.0 * f64::consts::PI * (1.0 - cos_max);
+        Some(LightSample {
+            dir,
+            distance: projection - chord,
+            radiance: self.intensity * solid_angle * self.color,
+        })
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn geometry(&self) -> Vec<Box<Bounded>> {
+        if !self.visible {
+            return Vec::new();
+        }
+        vec![Box::new(Sphere {
+            center: self.center,
+            radius: self.radius,
+            shader: EmissiveShader::new(self.intensity * self.color),
+            roll: 0.0,
+            pitch: 0.0,
+            yaw: 0.0,
+        })]
+    }
+}
+
+/// Parallelogram with the corners a, b, c and b + c - a that shines to the side from which
+/// a, b and c are counterclockwise
+pub struct RectangleLight {
+    pub a: Vector3<f64>,
+    pub b: Vector3<f64>,
+    pub c: Vector3<f64>,
+    pub color: Vector3<f64>,
+    /// Radiance of the surface is color * intensity
+    pub intensity: f64,
+    pub samples: u32,
+    /// Whether camera rays see the rectangle
+    pub visible: bool,
+}
+
+impl Light for RectangleLight {
+    fn sample(&self, pos: Vector3<f64>, u: Vector2<f64>) -> Option<LightSample> {
+        let (edge1, edge2) = (self.b - self.a, self.c - self.a);
+        let normal = edge1.cross(&edge2);
+        let point = self.a + u.x * edge1 + u.y * edge2;
+        surface_sample(
+            pos,
+            point,
+            Unit::new_normalize(normal),
+            normal.norm(),
+            self.intensity * self.color,
+        )
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn geometry(&self) -> Vec<Box<Bounded>> {
+        if !self.visible {
+            return Vec::new();
+        }
+        let d = self.b + self.c - self.a;
+        vec![
+            emitter(self.a, self.b, d, self.intensity * self.color),
+            emitter(self.a, d, self.c, self.intensity * self.color),
+        ]
+    }
+}
+
+/// Round light that shines to the side its normal points to
+pub struct DiskLight {
+    pub center: Vector3<f64>,
+    pub normal: Unit<Vector3<f64>>,
+    pub radius: f64,
+    pub color: Vector3<f64>,
+    /// Radiance of the surface is color * intensity
+    pub intensity: f64,
+    pub samples: u32,
+    /// Whether camera rays see the disk
+    pub visible: bool,
+}
+
+impl Light for DiskLight {
+    fn sample(&self, pos: Vector3<f64>, u: Vector2<f64>) -> Option<LightSample> {
+        // concentric mapping keeps the strata of u compact on the disk
+        let (x, y) = (2.0 * u.x - 1.0, 2.0 * u.y - 1.0);
+        let (r, phi) = if x == 0.0 && y == 0.0 {
+            (0.0, 0.0)
+        } else if x.abs() > y.abs() {
+            (x, f64::consts::FRAC_PI_4 * (y / x))
+        } else {
+            (y, f64::consts::FRAC_PI_2 - f64::consts::FRAC_PI_4 * (x / y))
+        };
+        let (a, b) = orthonormal_basis(&self.normal);
+        let point = self.center + self.radius * r * (phi.cos() * a + phi.sin() * b);
+        let area = f64::consts::PI * self.radius * self.radius;
+        surface_sample(pos, point, self.normal, area, self.intensity * self.color)
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    fn geometry(&self) -> Vec<Box<Bounded>> {
+        if !self.visible {
+            return Vec::new();
+        }
+        let (a, b) = orthonormal_basis(&self.normal);
+        let rim = |i: usize| {
+            let phi = 2.0 * f64::consts::PI * i as f64 / DISK_SEGMENTS as f64;
+            self.center + self.radius * (phi.cos() * a + phi.sin() * b)
+        };
+        (0..DISK_SEGMENTS)
+            .map(|i| emitter(self.center, rim(i), rim(i + 1), self.intensity * self.color))
+            .collect()
+    }
+}
+
+/// Triangles of an emissive mesh as a light source, e.g. the bulb of a lamp in an obj file.
+/// The mesh itself is rendered by its own shader, so the light has no geometry.
+pub struct MeshLight {
+    triangles: Vec<[Vector3<f64>; 3]>,
+    /// Sum of the areas of the triangles up to and including each triangle
+    cumulative_areas: Vec<f64>,
+    /// Radiance of both sides of the triangles
+    pub radiance: Vector3<f64>,
+    pub samples: u32,
+}
+
+impl MeshLight {
+    /// Triangles without a finite area are dropped, e.g. degenerate faces or faces with
+    /// broken vertices in an obj file, None if no triangle is left
+    pub fn new(
+        triangles: Vec<[Vector3<f64>; 3]>,
+        radiance: Vector3<f64>,
+        samples: u32,
+    ) -> Option<Self> {
+        let triangles: Vec<_> = triangles
+            .into_iter()
+            .filter(|&[a, b, c]| {
+                let area = triangle_area(a, b, c);
+                area.is_finite() && area > 0.0
+            })
+            .collect();
+        if triangles.is_empty() {
+            return None;
+        }
+        let mut total = 0.0;
+        let cumulative_areas = triangles
+            .iter()
+            .map(|&[a, b, c]| {
+                total += triangle_area(a, b, c);
+                total
+            })
+            .collect();
+        Some(MeshLight {
+            triangles,
+            cumulative_areas,
+            radiance,
+            samples,
+        })
+    }
+
+    pub fn area(&self) -> f64 {
+        self.cumulative_areas[self.cumulative_areas.len() - 1]
+    }
+}
+
+impl Light for MeshLight {
+    /// Picks a triangle by its area with u.x and a point on it with the rest of u
+    fn sample(&self, pos: Vector3<f64>, u: Vector2<f64>) -> Option<LightSample> {
+        let area = self.area();
+        let target = u.x * area;
+        let i = self
+            .cumulative_areas
+            .partition_point(|&sum| sum <= target)
+            .min(self.triangles.len() - 1);
+        let start = if i == 0 { 0.0 } else { self.cumulative_areas[i - 1] };
+        let u_x = ((target - start) / (self.cumulative_areas[i] - start)).min(1.0);
+        // uniform on the triangle
+        let [a, b, c] = self.triangles[i];
+        let s = u_x.sqrt();
+        let point = (1.0 - s) * a + s * (1.0 - u.y) * b + s * u.y * c;
+        let normal = (b - a).cross(&(c - a));
+        // the triangles glow on both sides
+        let normal = if normal.dot(&(pos - point)) < 0.0 {
+            -normal
+        } else {
+            normal
+        };
+        surface_sample(pos, point, Unit::new_normalize(normal), area, self.radiance)
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+fn triangle_area(a: Vector3<f64>, b: Vector3<f64>, c: Vector3<f64>) -> f64 {
+    0.5 * (b - a).cross(&(c - a)).norm()
+}
+
+/// Light from point on a flat one-sided light with the given area, converted from the area
+/// of the light to the solid angle it covers seen from pos
+fn surface_sample(
+    pos: Vector3<f64>,
+    point: Vector3<f64>,
+    normal: Unit<Vector3<f64>>,
+    area: f64,
+    radiance: Vector3<f64>,
+) -> Option<LightSample> {
+    let to_light = point - pos;
+    let distance = to_light.norm();
+    let dir = to_light / distance;
+    let cos_light = -dir.dot(&normal);
+    if cos_light <= 0.0 {
+        return None;
+    }
+    Some(LightSample {
+        dir,
+        distance,
+        radiance: cos_light * area / (distance * distance) * radiance,
+    })
+}
+
+fn emitter(
+    a: Vector3<f64>,
+    b: Vector3<f64>,
+    c: Vector3<f64>,
+    radiance: Vector3<f64>,
+) -> Box<Bounded> {
+    Box::new(Triangle {
+        a,
+        b,
+        c,
+        uvs: None,
+        shader: EmissiveShader::new(radiance),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mesh_light_drops_broken_triangles() {
+        let (x, y) = (Vector3::x(), Vector3::y());
+        let triangles = vec![
+            [Vector3::zeros(), x, Vector3::repeat(f64::NAN)],
+            [Vector3::zeros(), x, 2.0 * x],
+            [Vector3::zeros(), x, Vector3::repeat(f64::INFINITY)],
+        ];
+        assert!(MeshLight::new(triangles.clone(), Vector3::repeat(1.0), 1).is_none());
+
+        let mut triangles = triangles;
+        triangles.push([Vector3::zeros(), x, y]);
+        let light = MeshLight::new(triangles, Vector3::repeat(1.0), 1).unwrap();
+        assert_eq!(light.area(), 0.5);
+        for i in 0..=10 {
+            let u = Vector2::new(i as f64 / 10.0, 0.5);
+            let sample = light.sample(Vector3::new(0.2, 0.2, 1.0), u).unwrap();
+            assert!(sample.radiance.iter().all(|value| value.is_finite()));
+        }
+    }
+}
@@ -0,0 +1,272 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::helpers::polar2vector;
+use crate::shader::texture_shader::{Texture, TextureFilter, WrapMode};
+use crate::world::light::{Light, LightSample};
+use na::{Rotation3, Unit, Vector2, Vector3};
+use std::f64;
+use std::sync::Arc;
+
+/// What rays see that don't hit anything
+pub trait Background: Send + Sync {
+    /// Light that comes from infinitely far away against the direction dir
+    fn radiance(&self, dir: Vector3<f64>) -> Vector3<f64>;
+}
+
+/// A background shared with e.g. an EnvironmentLight
+impl<T: Background> Background for Arc<T> {
+    fn radiance(&self, dir: Vector3<f64>) -> Vector3<f64> {
+        (**self).radiance(dir)
+    }
+}
+
+/// The same color in every direction, black is the background without one
+pub struct SolidBackground {
+    pub color: Vector3<f64>,
+}
+
+impl SolidBackground {
+    pub fn new(color: Vector3<f64>) -> Box<Background> {
+        Box::new(SolidBackground { color })
+    }
+}
+
+impl Background for SolidBackground {
+    fn radiance(&self, _dir: Vector3<f64>) -> Vector3<f64> {
+        self.color
+    }
+}
+
+/// Blends from bottom straight down to top straight up
+pub struct GradientBackground {
+    pub bottom: Vector3<f64>,
+    pub top: Vector3<f64>,
+    pub up: Unit<Vector3<f64>>,
+}
+
+impl Background for GradientBackground {
+    fn radiance(&self, dir: Vector3<f64>) -> Vector3<f64> {
+        let t = 0.5 * (dir.normalize().dot(&self.up) + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+}
+
+/// Equirectangular image all around the scene, mapped like an EquirectangularCamera with
+/// the same rotation renders it. Without a rotation the top row of the image is straight
+/// towards -y.
+pub struct EnvironmentMap {
+    pub texture: Arc<Texture>,
+    pub intensity: f64,
+    pub rotation: Rotation3<f64>,
+}
+
+impl EnvironmentMap {
+    pub fn new(texture: Arc<Texture>, intensity: f64, roll: f64, pitch: f64, yaw: f64) -> Self {
+        EnvironmentMap {
+            texture,
+            intensity,
+            rotation: Rotation3::from_euler_angles(roll, pitch, yaw),
+        }
+    }
+
+    /// Render a background into an environment map of the given height, twice as wide, e.g. to
+    /// light a scene with an EnvironmentLight
+    pub fn bake(background: &Background, height: usize) -> Result<Self, Error> {
+        let width = 2 * height;
+        let mut texels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let image_pos = Vector2::new(
+                    (x as f64 + 0.5) / width as f64,
+                    (y as f64 + 0.5) / height as f64,
+                );
+                texels.push(background.radiance(image_to_direction(image_pos)));
+            }
+        }
+        let texture = Texture::new(width, height, texels)?;
+        Ok(EnvironmentMap::new(Arc::new(texture), 1.0, 0.0, 0.0, 0.0))
+    }
+
+    /// Position in the image, x and y from 0 to 1 with the top row at 0
+    fn to_image(&self, dir: Vector3<f64>) -> Vector2<f64> {
+        let dir = self.rotation.inverse() * dir.normalize();
+        // inverse of polar2vector, the camera counts the rows from the bottom
+        let gamma = dir.y.max(-1.0).min(1.0).acos();
+        let phi = dir.x.atan2(dir.z);
+        Vector2::new(
+            0.5 * (phi / f64::consts::PI + 1.0),
+            1.0 - gamma / f64::consts::PI,
+        )
+    }
+
+    fn from_image(&self, image_pos: Vector2<f64>) -> Vector3<f64> {
+        self.rotation * image_to_direction(image_pos)
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn radiance(&self, dir: Vector3<f64>) -> Vector3<f64> {
+        let image_pos = self.to_image(dir);
+        let uv = Vector2::new(image_pos.x, 1.0 - image_pos.y);
+        self.intensity * self.texture.sample(uv, TextureFilter::Bilinear, WrapMode::Repeat)
+    }
+}
+
+/// An environment map as a light source. Directions are sampled by how bright the texels are
+/// and how much solid angle they cover, so that bright parts like the sun get most of the
+/// shadow rays.
+pub struct EnvironmentLight {
+    map: Arc<EnvironmentMap>,
+    /// Sum of the weights of the rows up to and including each row
+    row_sums: Vec<f64>,
+    /// Sum of the weights in each row up to and including each texel
+    texel_sums: Vec<Vec<f64>>,
+    pub samples: u32,
+}
+
+impl EnvironmentLight {
+    pub fn new(map: Arc<EnvironmentMap>, samples: u32) -> Self {
+        let (width, height) = (map.texture.get_width(), map.texture.get_height());
+        let mut texel_sums = Vec::with_capacity(height);
+        let mut row_sums = Vec::with_capacity(height);
+        let mut total = 0.0;
+        for y in 0..height {
+            // rows near the poles cover less solid angle
+            let sin_gamma = ((y as f64 + 0.5) / height as f64 * f64::consts::PI).sin();
+            let mut row_total = 0.0;
+            let row: Vec<f64> = (0..width)
+                .map(|x| {
+                    // texels that are NaN or infinite in the file don't get any rays
+                    let luminance = luminance(map.texture.get_texel(x, y));
+                    if luminance.is_finite() && luminance > 0.0 {
+                        row_total += luminance * sin_gamma;
+                    }
+                    row_total
+                })
+                .collect();
+            total += row_total;
+            row_sums.push(total);
+            texel_sums.push(row);
+        }
+        EnvironmentLight {
+            map,
+            row_sums,
+            texel_sums,
+            samples,
+        }
+    }
+}
+
+impl Light for EnvironmentLight {
+    fn sample(&self, _pos: Vector3<f64>, u: Vector2<f64>) -> Option<LightSample> {
+        let (width, height) = (self.texel_sums[0].len(), self.row_sums.len());
+        let total = self.row_sums[height - 1];
+        if total <= 0.0 {
+            return None;
+        }
+        let (y, v) = pick(&self.row_sums, u.y);
+        let row = &self.texel_sums[y];
+        let (x, w) = pick(row, u.x);
+        let image_pos = Vector2::new(
+            (x as f64 + w) / width as f64,
+            (y as f64 + v) / height as f64,
+        );
+        let sin_gamma = (image_pos.y * f64::consts::PI).sin();
+        if sin_gamma <= 0.0 {
+            return None;
+        }
+        // probability density per image area of picking this texel, converted to solid angle
+        let texel_weight = weight(row, x) / total;
+        if texel_weight <= 0.0 {
+            return None;
+        }
+        let pdf_image = texel_weight * (width * height) as f64;
+        let pdf = pdf_image / (2.0 * f64::consts::PI * f64::consts::PI * sin_gamma);
+        let dir = self.map.from_image(image_pos);
+        let radiance = self.map.intensity * self.map.texture.get_texel(x, y);
+        Some(LightSample {
+            dir,
+            distance: f64::INFINITY,
+            radiance: radiance / pdf,
+        })
+    }
+
+    fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
+/// Direction of a position in an unrotated environment map, like EquirectangularCamera::ray_at
+fn image_to_direction(image_pos: Vector2<f64>) -> Vector3<f64> {
+    let gamma = (1.0 - image_pos.y) * f64::consts::PI;
+    let phi = (2.0 * image_pos.x - 1.0) * f64::consts::PI;
+    polar2vector(gamma, phi)
+}
+
+/// Index of the entry that u (from 0 to 1) falls into, by the cumulative sums of the weights
+/// of the entries, and where in the entry it falls from 0 to 1. Entries without weight are
+/// never picked, unless all of them are.
+fn pick(sums: &[f64], u: f64) -> (usize, f64) {
+    let total = sums[sums.len() - 1];
+    let target = u * total;
+    // the first entry with a sum above the target, u = 1 picks the last entry with a weight
+    let last = sums.partition_point(|&sum| sum < total);
+    let i = sums.partition_point(|&sum| sum <= target).min(last);
+    let w = weight(sums, i);
+    let offset = if w > 0.0 {
+        (target - (sums[i] - w)) / w
+    } else {
+        0.5
+    };
+    (i, offset.max(0.0).min(1.0))
+}
+
+fn weight(sums: &[f64], i: usize) -> f64 {
+    if i == 0 {
+        sums[0]
+    } else {
+        sums[i] - sums[i - 1]
+    }
+}
+
+fn luminance(color: Vector3<f64>) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_light_skips_non_finite_texels() {
+        let texels = vec![
+            Vector3::repeat(f64::NAN),
+            Vector3::repeat(1.0),
+            Vector3::repeat(f64::INFINITY),
+            Vector3::repeat(-1.0),
+        ];
+        let texture = Arc::new(Texture::new(2, 2, texels).unwrap());
+        let map = Arc::new(EnvironmentMap::new(texture, 1.0, 0.0, 0.0, 0.0));
+        let light = EnvironmentLight::new(map, 1);
+        for i in 0..10 {
+            let u = Vector2::new((i as f64 + 0.5) / 10.0, 1.0 - i as f64 / 10.0);
+            let sample = light.sample(Vector3::zeros(), u).unwrap();
+            assert!(sample.radiance.iter().all(|value| value.is_finite() && *value > 0.0));
+        }
+    }
+}
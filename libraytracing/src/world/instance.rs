@@ -0,0 +1,139 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::intersection::Intersection;
+use crate::ray::Ray;
+use crate::storage::Bounded;
+use crate::world::Interceptable;
+use na::{Matrix3, Matrix4, Point3, Rotation3, Unit, Vector3, U3};
+use std::f64;
+use std::sync::Arc;
+
+/// An element placed in the world with an affine transform, the element itself is
+/// shared with all other instances of it
+#[derive(Clone)]
+pub struct Instance {
+    object: Arc<Bounded>,
+    /// From object to world space
+    transform: Matrix4<f64>,
+    inverse: Matrix4<f64>,
+    /// Transforms normals from object to world space
+    normal_matrix: Matrix3<f64>,
+    min: Vector3<f64>,
+    max: Vector3<f64>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<Bounded>, transform: Matrix4<f64>) -> Result<Self, Error> {
+        let mut instance = Instance {
+            object,
+            transform: Matrix4::identity(),
+            inverse: Matrix4::identity(),
+            normal_matrix: Matrix3::identity(),
+            min: Vector3::zeros(),
+            max: Vector3::zeros(),
+        };
+        instance.set_transform(transform)?;
+        Ok(instance)
+    }
+
+    /// Transform that scales first, then rotates (roll, pitch, yaw like Sphere) and then translates
+    pub fn transform_from(
+        translation: Vector3<f64>,
+        roll: f64,
+        pitch: f64,
+        yaw: f64,
+        scale: Vector3<f64>,
+    ) -> Matrix4<f64> {
+        Matrix4::new_translation(&translation)
+            * Rotation3::from_euler_angles(roll, pitch, yaw).to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&scale)
+    }
+
+    pub fn get_transform(&self) -> Matrix4<f64> {
+        self.transform
+    }
+
+    /// Move the instance, its bounds are updated
+    pub fn set_transform(&mut self, transform: Matrix4<f64>) -> Result<(), Error> {
+        let inverse = transform
+            .try_inverse()
+            .ok_or_else(|| Error::from("The transform of an instance must be invertible"))?;
+        self.transform = transform;
+        self.inverse = inverse;
+        self.normal_matrix = inverse.fixed_slice::<U3, U3>(0, 0).transpose();
+
+        // bounds of the transformed corners of the object bounds
+        let (object_min, object_max) = (self.object.get_min(), self.object.get_max());
+        self.min = Vector3::repeat(f64::INFINITY);
+        self.max = Vector3::repeat(f64::NEG_INFINITY);
+        for corner in 0..8 {
+            let point = Point3::new(
+                if corner & 1 == 0 { object_min.x } else { object_max.x },
+                if corner & 2 == 0 { object_min.y } else { object_max.y },
+                if corner & 4 == 0 { object_min.z } else { object_max.z },
+            );
+            let point = transform.transform_point(&point).coords;
+            self.min = self.min.zip_map(&point, f64::min);
+            self.max = self.max.zip_map(&point, f64::max);
+        }
+        Ok(())
+    }
+
+    /// The ray in object space, and by how much distances are longer in object space
+    fn object_ray(&self, ray: &Ray) -> (Ray, f64) {
+        let start = self.inverse.transform_point(&Point3::from(ray.start)).coords;
+        let dir = self.inverse.transform_vector(&ray.dir);
+        let scale = dir.norm();
+        let object_ray = Ray {
+            dir: Unit::new_unchecked(dir / scale),
+            start,
+            t_min: ray.t_min * scale,
+            t_max: ray.t_max * scale,
+        };
+        (object_ray, scale)
+    }
+}
+
+impl Interceptable for Instance {
+    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
+        let (object_ray, scale) = self.object_ray(ray);
+        self.object
+            .intercept(&object_ray)
+            .map(|(object_distance, mut intersection)| {
+                let distance = object_distance / scale;
+                intersection.pos = ray.start + ray.dir.into_inner() * distance;
+                intersection.normal_at_surface = self.normal_matrix * intersection.normal_at_surface;
+                intersection.shading_normal = self.normal_matrix * intersection.shading_normal;
+                (distance, intersection)
+            })
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.object.occluded(&self.object_ray(ray).0)
+    }
+}
+
+impl Bounded for Instance {
+    fn get_min(&self) -> Vector3<f64> {
+        self.min
+    }
+
+    fn get_max(&self) -> Vector3<f64> {
+        self.max
+    }
+}
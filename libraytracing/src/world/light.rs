@@ -14,23 +14,153 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
-use na::Vector3;
+use crate::storage::Bounded;
+use na::{Unit, Vector2, Vector3};
+use std::f64;
 
-#[derive(Debug)]
-pub struct Light {
-    pub color: Vector3<f64>,
+/// Light of one light source arriving at a point
+pub struct LightSample {
+    /// Unit vector from the point towards the light
+    pub dir: Vector3<f64>,
+    /// Distance from the point to the light, infinite for directional lights
+    pub distance: f64,
+    /// Color times intensity and falloff. For area lights this is the light of the whole light
+    /// source as estimated from this one point on it
+    pub radiance: Vector3<f64>,
+}
+
+pub trait Light: Send + Sync {
+    /// Light arriving at pos without shadows, None if no light goes there,
+    /// e.g. outside of the cone of a spot light. Area lights pick the point on the light
+    /// with u from [0, 1)², other lights ignore it.
+    fn sample(&self, pos: Vector3<f64>, u: Vector2<f64>) -> Option<LightSample>;
+
+    /// How many samples to average per shading point
+    fn samples(&self) -> u32 {
+        1
+    }
+
+    /// Shapes that show the light to camera rays, empty for lights without a surface
+    fn geometry(&self) -> Vec<Box<Bounded>> {
+        Vec::new()
+    }
+}
+
+/// How the light gets weaker with the distance
+#[derive(Clone, Copy)]
+pub enum Falloff {
+    /// Equally bright at every distance
+    None,
+    /// Physically correct 1 / distance²
+    InverseSquare,
+    /// 1 / (constant + linear * distance + quadratic * distance²)
+    Polynomial {
+        constant: f64,
+        linear: f64,
+        quadratic: f64,
+    },
+}
+
+impl Falloff {
+    pub fn factor(&self, distance: f64) -> f64 {
+        match *self {
+            Falloff::None => 1.0,
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+            Falloff::Polynomial {
+                constant,
+                linear,
+                quadratic,
+            } => 1.0 / (constant + linear * distance + quadratic * distance * distance),
+        }
+    }
+}
+
+/// Shines from a point in all directions
+pub struct PointLight {
     pub pos: Vector3<f64>,
+    pub color: Vector3<f64>,
     pub intensity: f64,
+    pub falloff: Falloff,
 }
 
-impl Light {
-    pub fn new(x: f64, y: f64, z: f64, color: Vector3<f64>) -> Self {
-        let pos = Vector3::new(x, y, z);
-        let intensity = 1.0;
-        Light {
+impl PointLight {
+    /// Light of intensity 1 without falloff
+    pub fn new(x: f64, y: f64, z: f64, color: Vector3<f64>) -> Box<Light> {
+        Box::new(PointLight {
+            pos: Vector3::new(x, y, z),
             color,
-            pos,
-            intensity,
+            intensity: 1.0,
+            falloff: Falloff::None,
+        })
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, pos: Vector3<f64>, _u: Vector2<f64>) -> Option<LightSample> {
+        let to_light = self.pos - pos;
+        let distance = to_light.norm();
+        Some(LightSample {
+            dir: to_light / distance,
+            distance,
+            radiance: self.intensity * self.falloff.factor(distance) * self.color,
+        })
+    }
+}
+
+/// Light from far away that arrives in parallel everywhere, like sunlight
+pub struct DirectionalLight {
+    /// Direction the light travels in
+    pub dir: Unit<Vector3<f64>>,
+    pub color: Vector3<f64>,
+    pub intensity: f64,
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _pos: Vector3<f64>, _u: Vector2<f64>) -> Option<LightSample> {
+        Some(LightSample {
+            dir: -self.dir.into_inner(),
+            distance: f64::INFINITY,
+            radiance: self.intensity * self.color,
+        })
+    }
+}
+
+/// Point light that only shines into a cone around dir
+pub struct SpotLight {
+    pub pos: Vector3<f64>,
+    /// Axis of the cone
+    pub dir: Unit<Vector3<f64>>,
+    pub color: Vector3<f64>,
+    pub intensity: f64,
+    pub falloff: Falloff,
+    /// Angle between the axis and the edge of the fully lit part of the cone, in radians
+    pub inner_angle: f64,
+    /// Angle between the axis and the edge of the cone, the light fades out between the
+    /// inner and the outer angle
+    pub outer_angle: f64,
+}
+
+impl Light for SpotLight {
+    fn sample(&self, pos: Vector3<f64>, _u: Vector2<f64>) -> Option<LightSample> {
+        let to_light = self.pos - pos;
+        let distance = to_light.norm();
+        let dir = to_light / distance;
+        let cos_angle = -dir.dot(&self.dir);
+        let (cos_inner, cos_outer) = (self.inner_angle.cos(), self.outer_angle.cos());
+        if cos_angle <= cos_outer {
+            return None;
         }
+        let cone = if cos_angle >= cos_inner {
+            1.0
+        } else {
+            // smoothstep from the outer to the inner edge
+            let t = (cos_angle - cos_outer) / (cos_inner - cos_outer);
+            t * t * (3.0 - 2.0 * t)
+        };
+        Some(LightSample {
+            dir,
+            distance,
+            radiance: cone * self.intensity * self.falloff.factor(distance) * self.color,
+        })
     }
 }
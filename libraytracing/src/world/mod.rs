@@ -14,30 +14,86 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
+use crate::camera::sampler::shifted_halton;
+use crate::helpers::vector2color;
 use crate::intersection::Intersection;
 use crate::ray::Ray;
-use crate::world::light::Light;
+use crate::storage::bv_storage::Builder;
+use crate::storage::instance_storage::InstanceStorage;
+use crate::world::background::{Background, SolidBackground};
+use crate::world::light::{Light, LightSample};
 use image::Rgba;
-use na::Vector3;
+use na::{Unit, Vector2, Vector3};
+use std::cell::{Ref, RefCell};
 use std::f64;
 
+/// Relative part of the distance to the light that a shadow ray stays away from both ends
+const SHADOW_EPSILON: f64 = 1e-6;
+
+pub mod area_light;
+pub mod background;
+pub mod instance;
 pub mod light;
 pub mod plane;
+pub mod sky;
+pub mod smooth_triangle;
 pub mod sphere;
 pub mod triangle;
+pub mod triangle_mesh;
 
-pub trait Interceptable {
+pub trait Interceptable: Send + Sync {
+    /// Nearest hit with a distance within the interval of the ray
     fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)>;
+
+    /// Whether anything is hit within the interval of the ray,
+    /// implementations can stop at the first hit they find
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.intercept(ray).is_some()
+    }
+}
+
+impl<T: Interceptable + ?Sized> Interceptable for Box<T> {
+    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
+        (**self).intercept(ray)
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        (**self).occluded(ray)
+    }
 }
 
 pub struct World {
     pub elements: Box<Interceptable>,
-    pub lights: Vec<Light>,
+    /// Instances kept apart from the elements, so that they can be moved between renders
+    pub instances: InstanceStorage,
+    pub lights: Vec<Box<Light>>,
+    pub background: Box<Background>,
 }
 
 impl World {
-    pub fn new(elements: Box<Interceptable>, lights: Vec<Light>) -> Self {
-        World { elements, lights }
+    pub fn new(elements: Box<Interceptable>, lights: Vec<Box<Light>>) -> Self {
+        let instances = InstanceStorage::build(Vec::new(), &Builder::default());
+        Self::with_instances(elements, instances, lights)
+    }
+
+    /// World whose instances can still be moved with `world.instances.set_transform`, e.g.
+    /// with the storages from Collector::into_storages
+    pub fn with_instances(
+        elements: Box<Interceptable>,
+        instances: InstanceStorage,
+        lights: Vec<Box<Light>>,
+    ) -> Self {
+        World {
+            elements,
+            instances,
+            lights,
+            background: SolidBackground::new(Vector3::new(0.0, 0.0, 0.0)),
+        }
+    }
+
+    /// Replaces the black background
+    pub fn set_background(&mut self, background: Box<Background>) {
+        self.background = background;
     }
 
     pub fn color(&self, ray: Ray, recursion_depth: f64) -> Rgba<u8> {
@@ -45,8 +101,8 @@ impl World {
             // touch something
             intersection.get_color(ray.dir.into_inner(), self, recursion_depth)
         } else {
-            // background color
-            Rgba([0, 0, 0, 255])
+            let radiance = self.background.radiance(ray.dir.into_inner());
+            vector2color(&radiance.map(|x| (x * 255.0).min(255.0).max(0.0) as u8))
         }
     }
 
@@ -55,7 +111,7 @@ impl World {
             // touch something
             intersection.get_appearance(ray.dir.into_inner(), self, recursion_depth)
         } else {
-            Vector3::new(0.0, 0.0, 0.0)
+            self.background.radiance(ray.dir.into_inner())
         }
     }
 
@@ -68,6 +124,92 @@ impl World {
     }
 
     pub fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
-        self.elements.intercept(ray)
+        let interception = self.elements.intercept(ray);
+        if self.instances.is_empty() {
+            return interception;
+        }
+        // only instances nearer than the hit of the elements
+        let mut ray = *ray;
+        if let Some((distance, _)) = interception {
+            ray.t_max = distance;
+        }
+        self.instances.intercept(&ray).or(interception)
+    }
+
+    /// Light of all light sources that reaches pos unshadowed. Lights with several samples
+    /// trace exactly that many shadow rays, spread evenly over the light by a shifted Halton
+    /// sequence, and their samples are weighted so that they add up to the light of the whole
+    /// light source.
+    pub fn visible_lights(&self, pos: Vector3<f64>) -> Vec<LightSample> {
+        // seeded by the position, so the noise doesn't depend on the render order
+        let seed =
+            pos.x.to_bits() ^ pos.y.to_bits().rotate_left(21) ^ pos.z.to_bits().rotate_left(42);
+        let mut visible = Vec::new();
+        for (i, light) in self.lights.iter().enumerate() {
+            let samples = light.samples().max(1);
+            let weight = 1.0 / samples as f64;
+            for j in 0..samples {
+                let u = if samples > 1 {
+                    shifted_halton(seed as u32 ^ i as u32, (seed >> 32) as u32, j)
+                } else {
+                    Vector2::new(0.5, 0.5)
+                };
+                if let Some(mut sample) = light.sample(pos, u) {
+                    if self.is_lit(pos, &sample) {
+                        sample.radiance *= weight;
+                        visible.push(sample);
+                    }
+                }
+            }
+        }
+        visible
+    }
+
+    /// Whether the light of sample reaches pos. For lights at a finite distance the shadow ray
+    /// starts at the light, just off the surface of area lights, and stops just before pos so it
+    /// doesn't hit the surface at pos. Directional lights have no position, so their
+    /// shadow ray starts at pos.
+    pub fn is_lit(&self, pos: Vector3<f64>, sample: &LightSample) -> bool {
+        let shadow_ray = if sample.distance.is_finite() {
+            Ray {
+                dir: Unit::new_unchecked(-sample.dir),
+                start: pos + sample.distance * sample.dir,
+                t_min: sample.distance * SHADOW_EPSILON,
+                t_max: sample.distance * (1.0 - SHADOW_EPSILON),
+            }
+        } else {
+            Ray::secondary(pos, Unit::new_unchecked(sample.dir))
+        };
+        !(self.elements.occluded(&shadow_ray)
+            || !self.instances.is_empty() && self.instances.occluded(&shadow_ray))
+    }
+}
+
+/// The lights that reach a shading point, traced when a shader first asks for them. All shaders
+/// of a hit share them, so e.g. the diffuse and specular part of a phong shader don't trace the
+/// shadow rays twice, and mirrors don't trace any.
+pub struct VisibleLights<'a> {
+    world: &'a World,
+    pos: Vector3<f64>,
+    samples: RefCell<Option<Vec<LightSample>>>,
+}
+
+impl<'a> VisibleLights<'a> {
+    pub fn new(world: &'a World, pos: Vector3<f64>) -> Self {
+        VisibleLights {
+            world,
+            pos,
+            samples: RefCell::new(None),
+        }
+    }
+
+    pub fn get(&self) -> Ref<'_, [LightSample]> {
+        if self.samples.borrow().is_none() {
+            *self.samples.borrow_mut() = Some(self.world.visible_lights(self.pos));
+        }
+        Ref::map(self.samples.borrow(), |samples| match samples {
+            Some(samples) => &samples[..],
+            None => &[],
+        })
     }
 }
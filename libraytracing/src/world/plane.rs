@@ -38,24 +38,22 @@ impl Interceptable for Plane {
         }
 
         let intersection_distance = normal.dot(&(self.a - ray.start)) / convergence_rate;
-        if intersection_distance < 0.0 {
+        if !ray.contains(intersection_distance) {
             return None;
         }
 
         let intersection_pos = intersection_distance * ray.dir.into_inner() + ray.start;
-        let normal = if convergence_rate > 0.0 {
-            -normal
-        } else {
-            normal
-        };
         let intersection = Intersection {
             pos: intersection_pos,
             normal_at_surface: normal,
+            shading_normal: normal,
             shader: &self.shader,
             pos_on_surface: Vector2::new(
                 (self.a - intersection_pos).dot(&edge_ab),
                 (self.a - intersection_pos).dot(&edge_ac),
             ),
+            primitive_id: 0,
+            object_id: 0,
         };
         return Some((intersection_distance, intersection));
     }
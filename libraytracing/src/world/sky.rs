@@ -0,0 +1,270 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::error::Error;
+use crate::helpers::orthonormal_basis;
+use crate::world::background::{Background, EnvironmentLight, EnvironmentMap};
+use crate::world::light::DirectionalLight;
+use na::{Unit, Vector3};
+use std::f64;
+use std::sync::Arc;
+
+/// Illuminance of the sun outside of the atmosphere in klx
+const SOLAR_ILLUMINANCE: f64 = 127.0;
+
+/// Angular radius of the sun disk seen from the earth
+const SUN_RADIUS: f64 = 0.00465;
+
+/// Height of the environment map the sky light samples
+const SKY_LIGHT_HEIGHT: usize = 128;
+
+/// Radiance per kcd/m² (and irradiance per klx) at an intensity of 1, so that the midday sun
+/// lights about as much as a point light of intensity 1
+const SCALE: f64 = 0.01;
+
+/// Clear daylight sky after Preetham, Shirley and Smits, "A Practical Analytic Model for
+/// Daylight" (1999), with a ground of uniform albedo below the horizon. The sun itself is a
+/// small disk in the sky, its light on the scene comes from the matching `sun` light.
+pub struct Sky {
+    up: Unit<Vector3<f64>>,
+    /// Towards the sun
+    sun_dir: Unit<Vector3<f64>>,
+    intensity: f64,
+    /// Coefficients A to E of the Perez distribution of the luminance Y and the
+    /// chromaticities x and y
+    perez: [[f64; 5]; 3],
+    /// Y, x and y at the zenith
+    zenith: [f64; 3],
+    /// Perez distribution at the zenith, which the distribution is normalized by
+    zenith_perez: [f64; 3],
+    /// Color the sun light has left after the atmosphere, as irradiance in klx
+    sun_irradiance: Vector3<f64>,
+    ground_radiance: Vector3<f64>,
+}
+
+impl Sky {
+    /// turbidity is the haziness of the air, from 2 for very clear to 10 for hazy skies
+    pub fn new(
+        sun_dir: Vector3<f64>,
+        up: Vector3<f64>,
+        turbidity: f64,
+        ground_albedo: Vector3<f64>,
+        intensity: f64,
+    ) -> Result<Self, Error> {
+        if sun_dir.norm() == 0.0 || up.norm() == 0.0 {
+            return Err(Error::from("The sun direction and up must not be zero"));
+        }
+        if turbidity < 1.7 || turbidity > 10.0 {
+            return Err(Error::from("The turbidity must be between 1.7 and 10"));
+        }
+        let (up, sun_dir) = (Unit::new_normalize(up), Unit::new_normalize(sun_dir));
+        let cos_sun = sun_dir.dot(&up);
+        if cos_sun < 0.0 {
+            return Err(Error::from("The sun must be above the horizon"));
+        }
+        let theta_sun = cos_sun.acos();
+        let t = turbidity;
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+        let chi = (4.0 / 9.0 - t / 120.0) * (f64::consts::PI - 2.0 * theta_sun);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let zenith = [
+            zenith_luminance.max(0.0),
+            zenith_chromaticity(
+                t,
+                theta_sun,
+                [
+                    [0.00166, -0.00375, 0.00209, 0.0],
+                    [-0.02903, 0.06377, -0.03202, 0.00394],
+                    [0.11693, -0.21196, 0.06052, 0.25886],
+                ],
+            ),
+            zenith_chromaticity(
+                t,
+                theta_sun,
+                [
+                    [0.00275, -0.00610, 0.00317, 0.0],
+                    [-0.04214, 0.08970, -0.04153, 0.00516],
+                    [0.15346, -0.26756, 0.06670, 0.26688],
+                ],
+            ),
+        ];
+        let zenith_perez = [
+            perez_function(&perez[0], 1.0, theta_sun),
+            perez_function(&perez[1], 1.0, theta_sun),
+            perez_function(&perez[2], 1.0, theta_sun),
+        ];
+        let mut sky = Sky {
+            up,
+            sun_dir,
+            intensity,
+            perez,
+            zenith,
+            zenith_perez,
+            sun_irradiance: SOLAR_ILLUMINANCE * transmittance(theta_sun, turbidity),
+            ground_radiance: Vector3::new(0.0, 0.0, 0.0),
+        };
+        // the ground reflects the sun and the sky diffusely
+        let irradiance = sky.sun_irradiance * cos_sun + sky.sky_irradiance();
+        sky.ground_radiance = ground_albedo.component_mul(&irradiance) / f64::consts::PI;
+        Ok(sky)
+    }
+
+    /// Directional light of the sun, in the color that gets through the atmosphere
+    pub fn sun(&self) -> DirectionalLight {
+        let irradiance = SCALE * self.intensity * self.sun_irradiance;
+        let intensity = irradiance.max();
+        DirectionalLight {
+            dir: -self.sun_dir,
+            color: if intensity > 0.0 {
+                irradiance / intensity
+            } else {
+                irradiance
+            },
+            intensity,
+        }
+    }
+
+    /// Light of the sky without the sun, to go with the sun light
+    pub fn sky_light(&self, samples: u32) -> Result<EnvironmentLight, Error> {
+        let map = EnvironmentMap::bake(&SkyWithoutSun(self), SKY_LIGHT_HEIGHT)?;
+        Ok(EnvironmentLight::new(Arc::new(map), samples))
+    }
+
+    /// Radiance of the sky without the sun disk, in kcd/m²
+    fn sky_radiance(&self, dir: &Vector3<f64>) -> Vector3<f64> {
+        let cos_theta = dir.dot(&self.up);
+        if cos_theta <= 0.0 {
+            return self.ground_radiance;
+        }
+        let gamma = dir.dot(&self.sun_dir).max(-1.0).min(1.0).acos();
+        let value = |i: usize| {
+            self.zenith[i] * perez_function(&self.perez[i], cos_theta, gamma) / self.zenith_perez[i]
+        };
+        xyy_to_rgb(value(1), value(2), value(0))
+    }
+
+    /// Irradiance of the sky on the ground, without the sun
+    fn sky_irradiance(&self) -> Vector3<f64> {
+        let (a, b) = orthonormal_basis(&self.up);
+        let (steps_theta, steps_phi) = (32, 64);
+        let d_theta = f64::consts::FRAC_PI_2 / steps_theta as f64;
+        let d_phi = 2.0 * f64::consts::PI / steps_phi as f64;
+        let mut irradiance = Vector3::new(0.0, 0.0, 0.0);
+        for i in 0..steps_theta {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..steps_phi {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let dir = theta.cos() * self.up.into_inner()
+                    + theta.sin() * (phi.cos() * a + phi.sin() * b);
+                irradiance += self.sky_radiance(&dir) * theta.cos() * theta.sin() * d_theta * d_phi;
+            }
+        }
+        irradiance
+    }
+}
+
+impl Background for Sky {
+    fn radiance(&self, dir: Vector3<f64>) -> Vector3<f64> {
+        let dir = dir.normalize();
+        let radiance = if dir.dot(&self.sun_dir) >= SUN_RADIUS.cos() {
+            // the irradiance of the sun spread over its disk
+            self.sun_irradiance / (f64::consts::PI * SUN_RADIUS * SUN_RADIUS)
+        } else {
+            self.sky_radiance(&dir)
+        };
+        SCALE * self.intensity * radiance
+    }
+}
+
+/// The sky without the sun disk, which the sun light already covers
+struct SkyWithoutSun<'a>(&'a Sky);
+
+impl<'a> Background for SkyWithoutSun<'a> {
+    fn radiance(&self, dir: Vector3<f64>) -> Vector3<f64> {
+        SCALE * self.0.intensity * self.0.sky_radiance(&dir.normalize())
+    }
+}
+
+/// Perez et al.'s sky distribution for the zenith angle theta and the angle gamma to the sun
+fn perez_function(coefficients: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+    let [a, b, c, d, e] = *coefficients;
+    (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+/// Chromaticity x or y at the zenith, a polynomial in the turbidity and the sun's zenith angle
+fn zenith_chromaticity(turbidity: f64, theta_sun: f64, matrix: [[f64; 4]; 3]) -> f64 {
+    let t = [turbidity * turbidity, turbidity, 1.0];
+    let theta = [theta_sun.powi(3), theta_sun.powi(2), theta_sun, 1.0];
+    let mut result = 0.0;
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, value) in row.iter().enumerate() {
+            result += t[i] * value * theta[j];
+        }
+    }
+    result
+}
+
+/// Linear sRGB of a CIE xyY color
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Vector3<f64> {
+    if y <= 0.0 {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+    Vector3::new(
+        3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z,
+        -0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z,
+        0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z,
+    )
+    .map(|c| c.max(0.0))
+}
+
+/// Part of the sun light that gets through Rayleigh scattering and aerosols, at the red, green
+/// and blue wavelengths
+fn transmittance(theta_sun: f64, turbidity: f64) -> Vector3<f64> {
+    // relative optical mass of the air the light passes
+    let degrees = theta_sun.to_degrees();
+    let mass = 1.0 / (theta_sun.cos() + 0.15 * (93.885 - degrees).powf(-1.253));
+    let beta = 0.046_083_65 * turbidity - 0.045_860_25;
+    // wavelengths in micrometers
+    Vector3::new(0.65, 0.57, 0.475).map(|lambda: f64| {
+        let rayleigh = (-mass * 0.008_735 * lambda.powf(-4.08)).exp();
+        let aerosol = (-mass * beta * lambda.powf(-1.3)).exp();
+        rayleigh * aerosol
+    })
+}
@@ -0,0 +1,73 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::intersection::Intersection;
+use crate::ray::Ray;
+use crate::shader::Shader;
+use crate::storage::Bounded;
+use crate::world::triangle::intersect_triangle;
+use crate::world::Interceptable;
+use na::{Vector2, Vector3};
+
+/// Triangle that interpolates the normals given at its vertices, so meshes look smooth.
+/// Only the shading normal is interpolated, the geometric normal stays the face normal.
+pub struct SmoothTriangle {
+    pub a: Vector3<f64>,
+    pub b: Vector3<f64>,
+    pub c: Vector3<f64>,
+    pub normal_a: Vector3<f64>,
+    pub normal_b: Vector3<f64>,
+    pub normal_c: Vector3<f64>,
+    /// Texture coordinates of a, b and c
+    pub uvs: Option<[Vector2<f64>; 3]>,
+    pub shader: Box<Shader>,
+}
+
+impl Interceptable for SmoothTriangle {
+    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
+        intersect_triangle(self.a, self.b, self.c, ray).map(|hit| {
+            let (normal_at_surface, shading_normal) =
+                hit.normals(self.normal_a, self.normal_b, self.normal_c);
+            let intersection = Intersection {
+                pos: ray.start + ray.dir.into_inner() * hit.distance,
+                normal_at_surface,
+                shading_normal,
+                shader: &self.shader,
+                pos_on_surface: hit.surface_pos(self.a, self.b, self.c, &self.uvs),
+                primitive_id: 0,
+                object_id: 0,
+            };
+            (hit.distance, intersection)
+        })
+    }
+}
+
+impl Bounded for SmoothTriangle {
+    fn get_min(&self) -> Vector3<f64> {
+        Vector3::new(
+            self.a.x.min(self.b.x).min(self.c.x),
+            self.a.y.min(self.b.y).min(self.c.y),
+            self.a.z.min(self.b.z).min(self.c.z),
+        )
+    }
+    fn get_max(&self) -> Vector3<f64> {
+        Vector3::new(
+            self.a.x.max(self.b.x).max(self.c.x),
+            self.a.y.max(self.b.y).max(self.c.y),
+            self.a.z.max(self.b.z).max(self.c.z),
+        )
+    }
+}
@@ -46,7 +46,7 @@ impl Interceptable for Sphere {
             lambdas.push((-b + delta.sqrt()) / 2.0);
             lambdas.push((-b - delta.sqrt()) / 2.0);
 
-            let pos_lambdas: Vec<&f64> = lambdas.iter().filter(|x| *x >= &0.0).collect();
+            let pos_lambdas: Vec<&f64> = lambdas.iter().filter(|x| ray.contains(**x)).collect();
 
             match min(pos_lambdas) {
                 None => {
@@ -64,11 +64,14 @@ impl Interceptable for Sphere {
                         Intersection {
                             pos: pos,
                             normal_at_surface: pos_to_center,
+                            shading_normal: pos_to_center,
                             shader: &self.shader,
                             pos_on_surface: Vector2::new(
                                 vertical_angle * self.radius,
                                 horizontal_angle * self.radius,
                             ),
+                            primitive_id: 0,
+                            object_id: 0,
                         },
                     ));
                 }
@@ -25,53 +25,109 @@ pub struct Triangle {
     pub a: Vector3<f64>,
     pub b: Vector3<f64>,
     pub c: Vector3<f64>,
+    /// Texture coordinates of a, b and c
+    pub uvs: Option<[Vector2<f64>; 3]>,
     pub shader: Box<Shader>,
 }
 
-impl Interceptable for Triangle {
-    // Shamelessly stolen from https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
-    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
-        let epsilon = 0.0001;
-        let edge1 = self.b - self.a;
-        let edge2 = self.c - self.a;
-        let h = ray.dir.cross(&edge2);
-        let a = edge1.dot(&h);
-        if a.abs() < epsilon {
-            // This ray is parallel to this triangle.
-            return None;
-        }
-        let f = 1.0 / a;
-        let s = ray.start - self.a;
-        let u = f * s.dot(&h);
-        if u < 0.0 || u > 1.0 {
-            return None;
+/// Where a ray hits a triangle
+pub struct TriangleHit {
+    pub distance: f64,
+    /// Barycentric coordinates of the hit, the weights of b and c
+    pub u: f64,
+    pub v: f64,
+    /// Face normal, on the side from which the corners are counterclockwise
+    pub normal: Vector3<f64>,
+}
+
+impl TriangleHit {
+    /// Interpolated texture coordinates, or the position on the triangle if it has none
+    pub fn surface_pos(
+        &self,
+        a: Vector3<f64>,
+        b: Vector3<f64>,
+        c: Vector3<f64>,
+        uvs: &Option<[Vector2<f64>; 3]>,
+    ) -> Vector2<f64> {
+        match uvs {
+            Some([uv_a, uv_b, uv_c]) => (1.0 - self.u - self.v) * uv_a + self.u * uv_b + self.v * uv_c,
+            None => Vector2::new(self.u * (b - a).norm(), self.v * (c - a).norm()),
         }
-        let q = s.cross(&edge1);
-        let v = f * ray.dir.dot(&q);
-        if v < 0.0 || u + v > 1.0 {
-            return None;
+    }
+
+    /// Face normal and vertex normals interpolated at the hit. The vertex normals decide
+    /// which side is outside, the face normal is turned to it if the winding disagrees.
+    pub fn normals(
+        &self,
+        normal_a: Vector3<f64>,
+        normal_b: Vector3<f64>,
+        normal_c: Vector3<f64>,
+    ) -> (Vector3<f64>, Vector3<f64>) {
+        let interpolated = (1.0 - self.u - self.v) * normal_a + self.u * normal_b + self.v * normal_c;
+        if interpolated.dot(&self.normal) < 0.0 {
+            (-self.normal, interpolated)
+        } else {
+            (self.normal, interpolated)
         }
-        // At this stage we can compute t to find out where the intersection point is on the line.
-        let t = f * edge2.dot(&q);
-        return if t > epsilon
-        // ray intersection
-        {
-            let pos = ray.start + ray.dir.into_inner() * t;
-            let h = edge1.cross(&edge2);
+    }
+}
 
-            let normal = if h.dot(&ray.dir) < 0.0 { h } else { -h };
+// Shamelessly stolen from https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+pub fn intersect_triangle(
+    a: Vector3<f64>,
+    b: Vector3<f64>,
+    c: Vector3<f64>,
+    ray: &Ray,
+) -> Option<TriangleHit> {
+    let epsilon = 0.0001;
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let h = ray.dir.cross(&edge2);
+    let det = edge1.dot(&h);
+    if det.abs() < epsilon {
+        // This ray is parallel to this triangle.
+        return None;
+    }
+    let f = 1.0 / det;
+    let s = ray.start - a;
+    let u = f * s.dot(&h);
+    if u < 0.0 || u > 1.0 {
+        return None;
+    }
+    let q = s.cross(&edge1);
+    let v = f * ray.dir.dot(&q);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    // At this stage we can compute t to find out where the intersection point is on the line.
+    let t = f * edge2.dot(&q);
+    if ray.contains(t) {
+        // ray intersection
+        Some(TriangleHit {
+            distance: t,
+            u,
+            v,
+            normal: edge1.cross(&edge2),
+        })
+    } else {
+        None
+    }
+}
 
+impl Interceptable for Triangle {
+    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
+        intersect_triangle(self.a, self.b, self.c, ray).map(|hit| {
             let intersection = Intersection {
-                pos: pos,
-                normal_at_surface: normal,
+                pos: ray.start + ray.dir.into_inner() * hit.distance,
+                normal_at_surface: hit.normal,
+                shading_normal: hit.normal,
                 shader: &self.shader,
-                pos_on_surface: Vector2::new(u * edge1.norm(), v * edge2.norm()),
+                pos_on_surface: hit.surface_pos(self.a, self.b, self.c, &self.uvs),
+                primitive_id: 0,
+                object_id: 0,
             };
-
-            Some((t, intersection))
-        } else {
-            None
-        };
+            (hit.distance, intersection)
+        })
     }
 }
 
@@ -0,0 +1,148 @@
+/**
+ * Copyright © 2019
+ * Sami Shalayel <sami.shalayel@tutamail.com>,
+ * Carl Schwan <carl@carlschwan.eu>,
+ * Daniel Freiermuth <d_freiermu14@cs.uni-kl.de>
+ *
+ * This work is free. You can redistribute it and/or modify it under the
+ * terms of the Do What The Fuck You Want To Public License, Version 2,
+ * as published by Sam Hocevar. See the LICENSE file for more details.
+ * 
+ * This program is free software. It comes without any warranty, to
+ * the extent permitted by applicable law. You can redistribute it
+ * and/or modify it under the terms of the Do What The Fuck You Want
+ * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
+ * file for more details. **/
+
+use crate::intersection::Intersection;
+use crate::ray::Ray;
+use crate::shader::Shader;
+use crate::storage::bv_storage::{Builder, Bvh};
+use crate::storage::Bounded;
+use crate::world::triangle::intersect_triangle;
+use crate::world::Interceptable;
+use na::{Vector2, Vector3};
+
+/// Triangle of a mesh, as indices into the arrays of the mesh
+#[derive(Clone, Copy)]
+pub struct Face {
+    pub vertices: [u32; 3],
+    /// Vertex normals for smooth shading
+    pub normals: Option<[u32; 3]>,
+    pub uvs: Option<[u32; 3]>,
+    /// Index into the materials of the mesh
+    pub material: u32,
+}
+
+/// Triangles that share their vertices, normals, texture coordinates and shaders.
+/// The faces have their own bounding volume hierarchy.
+pub struct TriangleMesh {
+    vertices: Vec<Vector3<f64>>,
+    normals: Vec<Vector3<f64>>,
+    uvs: Vec<Vector2<f64>>,
+    /// In the order of the leaves of bvh
+    faces: Vec<Face>,
+    materials: Vec<Box<Shader>>,
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    /// All indices of the faces have to be valid for the given arrays
+    pub fn new(
+        vertices: Vec<Vector3<f64>>,
+        normals: Vec<Vector3<f64>>,
+        uvs: Vec<Vector2<f64>>,
+        faces: Vec<Face>,
+        materials: Vec<Box<Shader>>,
+    ) -> Self {
+        let bounds: Vec<_> = faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] = face.vertices;
+                let (a, b, c) = (vertices[a as usize], vertices[b as usize], vertices[c as usize]);
+                (
+                    a.zip_map(&b, f64::min).zip_map(&c, f64::min),
+                    a.zip_map(&b, f64::max).zip_map(&c, f64::max),
+                )
+            })
+            .collect();
+        let (bvh, order) = Bvh::build(&bounds, &Builder::default());
+        let faces = order.iter().map(|&i| faces[i]).collect();
+        TriangleMesh {
+            vertices,
+            normals,
+            uvs,
+            faces,
+            materials,
+            bvh,
+        }
+    }
+
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    fn corners(&self, face: &Face) -> (Vector3<f64>, Vector3<f64>, Vector3<f64>) {
+        let [a, b, c] = face.vertices;
+        (
+            self.vertices[a as usize],
+            self.vertices[b as usize],
+            self.vertices[c as usize],
+        )
+    }
+
+    fn intercept_face(&self, i: usize, ray: &Ray) -> Option<(f64, Intersection)> {
+        let face = &self.faces[i];
+        let (a, b, c) = self.corners(face);
+        intersect_triangle(a, b, c, ray).map(|hit| {
+            let (normal_at_surface, shading_normal) = match face.normals {
+                Some([n_a, n_b, n_c]) => hit.normals(
+                    self.normals[n_a as usize],
+                    self.normals[n_b as usize],
+                    self.normals[n_c as usize],
+                ),
+                None => (hit.normal, hit.normal),
+            };
+            let uvs = face.uvs.map(|[uv_a, uv_b, uv_c]| {
+                [
+                    self.uvs[uv_a as usize],
+                    self.uvs[uv_b as usize],
+                    self.uvs[uv_c as usize],
+                ]
+            });
+            let intersection = Intersection {
+                pos: ray.start + ray.dir.into_inner() * hit.distance,
+                normal_at_surface,
+                shading_normal,
+                shader: &self.materials[face.material as usize],
+                pos_on_surface: hit.surface_pos(a, b, c, &uvs),
+                primitive_id: i + 1,
+                object_id: 0,
+            };
+            (hit.distance, intersection)
+        })
+    }
+}
+
+impl Interceptable for TriangleMesh {
+    fn intercept(&self, ray: &Ray) -> Option<(f64, Intersection)> {
+        self.bvh.intercept(ray, |i, ray| self.intercept_face(i, ray))
+    }
+
+    fn occluded(&self, ray: &Ray) -> bool {
+        self.bvh.occluded(ray, |i| {
+            let (a, b, c) = self.corners(&self.faces[i]);
+            intersect_triangle(a, b, c, ray).is_some()
+        })
+    }
+}
+
+impl Bounded for TriangleMesh {
+    fn get_min(&self) -> Vector3<f64> {
+        self.bvh.get_min()
+    }
+
+    fn get_max(&self) -> Vector3<f64> {
+        self.bvh.get_max()
+    }
+}
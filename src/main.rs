@@ -14,116 +14,490 @@
  * To Public License, Version 2, as published by Sam Hocevar. See the LICENSE
  * file for more details. **/
 
+extern crate clap;
 extern crate image;
 extern crate nalgebra as na;
 
+use clap::{App, Arg, ArgMatches};
+use image::DynamicImage;
 use libraytracing::camera::equilinear_camera::*;
+use libraytracing::camera::equirectangular_camera::*;
+use libraytracing::camera::filter::*;
+use libraytracing::camera::sampler::*;
 use libraytracing::camera::Camera;
 use libraytracing::error::Error;
 use libraytracing::obj::FileParser;
-use libraytracing::shader::mirror_shader::MirrorShader;
-use libraytracing::shader::ambient_shader::AmbientShader;
-use libraytracing::shader::*;
+use libraytracing::output;
+use libraytracing::output::PixelType;
+use libraytracing::pass::Pass;
+use libraytracing::scene::Scene;
+use libraytracing::shader::texture_shader::Texture;
+use libraytracing::storage::bv_storage::{Builder, SahParameters};
+use libraytracing::tone_mapping::*;
 use libraytracing::world::World;
-use libraytracing::world::light::Light;
-use libraytracing::world::plane::*;
-use libraytracing::world::sphere::*;
-use libraytracing::storage::primitive_storage::PrimitiveStorage;
+use libraytracing::world::background::{
+    Background, EnvironmentLight, EnvironmentMap, SolidBackground,
+};
+use libraytracing::world::light::{Light, PointLight};
+use libraytracing::world::sky::Sky;
 use na::Vector3;
-use std::env;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Options that describe the camera, they can't be used together with a scene file
+const CAMERA_OPTIONS: [&str; 13] = [
+    "width", "height", "camera", "pos", "look-at", "roll", "pitch", "yaw", "fov", "depth",
+    "samples", "sampler", "filter",
+];
+
 fn main() -> Result<(), Error> {
-    println!("Start parsing");
-    // Parse file given as args
-    let mut file_parser = FileParser::new();
+    let matches = App::new("raytracing")
+        .about("Render wavefront obj files or scene description files")
+        .arg(Arg::with_name("FILES")
+            .help("Wavefront obj and mtl files, or one scene description file (.toml)")
+            .multiple(true)
+            .required(true))
+        .arg(Arg::with_name("width")
+            .short("W")
+            .long("width")
+            .takes_value(true)
+            .default_value("1200")
+            .help("Width of the image in pixels (ignored by the equirectangular camera)"))
+        .arg(Arg::with_name("height")
+            .short("H")
+            .long("height")
+            .takes_value(true)
+            .default_value("800")
+            .help("Height of the image in pixels"))
+        .arg(Arg::with_name("camera")
+            .long("camera")
+            .takes_value(true)
+            .possible_values(&["equilinear", "equirectangular"])
+            .default_value("equilinear")
+            .help("Projection of the camera"))
+        .arg(Arg::with_name("pos")
+            .long("pos")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("200,0,300")
+            .help("Position of the camera as x,y,z"))
+        .arg(Arg::with_name("look-at")
+            .long("look-at")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .conflicts_with_all(&["roll", "pitch", "yaw"])
+            .help("Point the camera at x,y,z instead of using roll, pitch and yaw"))
+        .arg(Arg::with_name("roll")
+            .long("roll")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("0.0")
+            .help("Rotation of the camera down-up in radians"))
+        .arg(Arg::with_name("pitch")
+            .long("pitch")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("3.7")
+            .help("Rotation of the camera right-left in radians"))
+        .arg(Arg::with_name("yaw")
+            .long("yaw")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("0.0")
+            .help("Rotation of the camera counterclockwise-clockwise in radians"))
+        .arg(Arg::with_name("fov")
+            .long("fov")
+            .takes_value(true)
+            .default_value("40.0")
+            .help("Vertical view angle of the equilinear camera in degrees"))
+        .arg(Arg::with_name("depth")
+            .long("depth")
+            .takes_value(true)
+            .default_value("10.0")
+            .help("Recursion depth for mirrors and ambient occlusion"))
+        .arg(Arg::with_name("samples")
+            .short("s")
+            .long("samples")
+            .takes_value(true)
+            .default_value("1")
+            .help("Number of samples per pixel (rounded up to a square for regular and jittered)"))
+        .arg(Arg::with_name("sampler")
+            .long("sampler")
+            .takes_value(true)
+            .possible_values(&["regular", "jittered", "halton", "sobol"])
+            .default_value("regular")
+            .help("Distribution of the samples in a pixel"))
+        .arg(Arg::with_name("filter")
+            .long("filter")
+            .takes_value(true)
+            .possible_values(&["box", "tent", "gaussian", "mitchell"])
+            .default_value("box")
+            .help("Reconstruction filter that combines the samples of a pixel"))
+        .arg(Arg::with_name("storage")
+            .long("storage")
+            .takes_value(true)
+            .possible_values(&["sah", "midpoint", "primitive"])
+            .default_value("sah")
+            .help("Bounding volume hierarchy split by surface area heuristic or at the midpoint, or a plain list of primitives"))
+        .arg(Arg::with_name("sah-bins")
+            .long("sah-bins")
+            .takes_value(true)
+            .help("Buckets per axis the surface area heuristic sorts the primitives into [default: 16]"))
+        .arg(Arg::with_name("leaf-size")
+            .long("leaf-size")
+            .takes_value(true)
+            .help("Nodes with more primitives are always split by the surface area heuristic [default: 4]"))
+        .arg(Arg::with_name("traversal-cost")
+            .long("traversal-cost")
+            .takes_value(true)
+            .help("Cost of visiting a node for the surface area heuristic [default: 1]"))
+        .arg(Arg::with_name("intersection-cost")
+            .long("intersection-cost")
+            .takes_value(true)
+            .help("Cost of intersecting a primitive for the surface area heuristic [default: 1]"))
+        .arg(Arg::with_name("tonemap")
+            .long("tonemap")
+            .takes_value(true)
+            .possible_values(&["linear", "reinhard", "aces"])
+            .default_value("linear")
+            .help("Tone mapping from linear colors to the output image"))
+        .arg(Arg::with_name("exposure")
+            .long("exposure")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .default_value("0.0")
+            .help("Exposure correction in stops, applied before tone mapping"))
+        .arg(Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .help("Output file, the format is chosen from the extension [default: output<timestamp>.png]. \
+                   exr, pfm and hdr files keep the linear colors without tone mapping"))
+        .arg(Arg::with_name("default-material")
+            .long("default-material")
+            .takes_value(true)
+            .help("Render faces whose material doesn't exist with a phong shader of the color r,g,b \
+                   instead of stopping with an error"))
+        .arg(Arg::with_name("mesh-lights")
+            .long("mesh-lights")
+            .takes_value(true)
+            .help("Let faces of materials with an emission (Ke) light the scene with this many \
+                   shadow rays per shading point"))
+        .arg(Arg::with_name("background")
+            .long("background")
+            .takes_value(true)
+            .help("Color r,g,b or equirectangular image file that rays see when they hit nothing"))
+        .arg(Arg::with_name("environment-light")
+            .long("environment-light")
+            .takes_value(true)
+            .help("Let the background image or the sky light the scene with this many shadow \
+                   rays per shading point"))
+        .arg(Arg::with_name("sky")
+            .long("sky")
+            .takes_value(true)
+            .allow_hyphen_values(true)
+            .conflicts_with("background")
+            .help("Daylight sky with a sun in the direction x,y,z, -y is up"))
+        .arg(Arg::with_name("passes")
+            .long("passes")
+            .takes_value(true)
+            .use_delimiter(true)
+            .possible_values(&["depth", "normal", "uv", "albedo", "primitive_id", "object_id"])
+            .help("Extra passes to render, as channels of an exr file or as files next to the image"))
+        .arg(Arg::with_name("half")
+            .long("half")
+            .help("Write exr files with 16 bit instead of 32 bit floats"))
+        .arg(Arg::with_name("threads")
+            .short("j")
+            .long("threads")
+            .takes_value(true)
+            .default_value("0")
+            .help("Number of render threads, 0 uses one per logical core"))
+        .arg(Arg::with_name("no-progress")
+            .long("no-progress")
+            .help("Don't show a progress bar"))
+        .get_matches();
+
+    let files: Vec<&str> = matches.values_of("FILES").unwrap().collect();
+    let scene_file = files.iter().find(|file| file.ends_with(".toml"));
+
+    let (elements, lights, cam, background) = if let Some(scene_file) = scene_file {
+        if files.len() > 1 {
+            return Err(Error::from("A scene file can't be combined with other files"));
+        }
+        if let Some(option) = CAMERA_OPTIONS.iter().find(|o| matches.occurrences_of(o) > 0) {
+            return Err(Error::from(format!(
+                "--{} can't be used with a scene file, set it in the scene's camera instead",
+                option
+            )));
+        }
+        if matches.is_present("default-material") {
+            return Err(Error::from(
+                "--default-material can't be used with a scene file, \
+                 set it in the scene's obj entries instead",
+            ));
+        }
+        if matches.is_present("mesh-lights") {
+            return Err(Error::from(
+                "--mesh-lights can't be used with a scene file, \
+                 set mesh_light_samples in the scene's obj entries instead",
+            ));
+        }
+        if matches.is_present("background") || matches.is_present("sky") {
+            return Err(Error::from(
+                "--background and --sky can't be used with a scene file, \
+                 set the scene's background instead",
+            ));
+        }
+        let scene = Scene::load(scene_file)?;
+        for warning in &scene.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        (scene.elements, scene.lights, scene.camera, scene.background)
+    } else {
+        println!("Start parsing");
+        let mut file_parser = FileParser::new();
+        if matches.is_present("default-material") {
+            file_parser.set_default_material(Some(parse_vector(&matches, "default-material")?));
+        }
+        if matches.is_present("mesh-lights") {
+            let samples: u32 = parse_value(&matches, "mesh-lights")?;
+            if samples == 0 {
+                return Err(Error::from("--mesh-lights needs at least one sample"));
+            }
+            file_parser.set_mesh_lights(Some(samples));
+        }
+        for file in files {
+            file_parser.parse(file.to_string())?;
+        }
+        println!("End parsing");
+        for warning in &file_parser.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+
+        let mut lights = file_parser.lights;
+        lights.push(PointLight::new(0.0, -10.0, 6.0, Vector3::new(1.0, 0.5, 1.0)));
+        lights.push(PointLight::new(6.0, -10.0, 6.0, Vector3::new(0.5, 1.0, 1.0)));
+
+        let background = background_from(&matches, &mut lights)?;
+        (file_parser.elements, lights, camera_from(&matches)?, background)
+    };
 
-    for argument in env::args().skip(1) {
-        file_parser.parse(argument)?;
+    let mut w = match builder_from(&matches)? {
+        None => World::new(elements.into_primitive_storage(), lights),
+        Some(builder) => {
+            let (elements, instances) = elements.into_storages(&builder);
+            World::with_instances(Box::new(elements), instances, lights)
+        }
+    };
+    w.set_background(background);
+    let threads = parse_value(&matches, "threads")?;
+    let tone_mapper = match matches.value_of("tonemap").unwrap() {
+        "reinhard" => ReinhardToneMapper::new(),
+        "aces" => AcesToneMapper::new(),
+        _ => LinearToneMapper::new(),
+    };
+    let exposure = parse_value(&matches, "exposure")?;
+    let passes = match matches.values_of("passes") {
+        Some(names) => names.map(str::parse).collect::<Result<Vec<Pass>, Error>>()?,
+        None => Vec::new(),
+    };
+    let layers = cam.render_layers(&w, !matches.is_present("no-progress"), threads, &passes)?;
+
+    let name = match matches.value_of("output") {
+        Some(name) => name.to_string(),
+        None => format!("output{:?}.png", SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs()),
+    };
+    if output::is_float_format(&name) {
+        let pixel_type = if matches.is_present("half") {
+            PixelType::Half
+        } else {
+            PixelType::Float
+        };
+        output::save(&layers, &name, pixel_type)?;
+    } else {
+        save_image(layers.beauty.to_image(exposure, &*tone_mapper), &name)?;
+        for (pass, buffer) in &layers.passes {
+            save_image(pass.to_image(buffer), &output::pass_path(&name, *pass))?;
+        }
     }
-    let mut elements = file_parser.elements;
-    println!("End parsing");
-
-    // add some spheres
-    let green_shader: Box<Shader> = Box::new(AmbientShader {
-        color: Vector3::new(0.0, 1.0, 0.0),
-    });
-    let red_shader = get_phong(Vector3::new(1.0, 0.0, 0.0));
-    let blue_shader = get_phong(Vector3::new(0.0, 0.0, 1.0));
-    let red_blue_shader = Box::new(chess_shader::ChessShader {
-        shader1: red_shader,
-        shader2: blue_shader,
-        size: 1.0,
-    });
-    let red_shader = get_phong(Vector3::new(1.0, 0.0, 0.0));
-    let blue_shader = get_phong(Vector3::new(0.0, 0.0, 1.0));
-    let red_blue_shader2 = Box::new(chess_shader::ChessShader {
-        shader1: red_shader,
-        shader2: blue_shader,
-        size: 1.0,
-    });
-    let red_shader = get_phong(Vector3::new(1.0, 0.0, 0.0));
-    let blue_shader = get_phong(Vector3::new(0.0, 0.0, 1.0));
-
-    elements.add(Box::new(Sphere {
-        center: Vector3::new(1.0, 1.0, 4.0),
-        radius: 1.0,
-        shader: red_blue_shader,
-        pitch: 0.0,
-        roll: 0.0,
-        yaw: 0.0,
-    }));
-    elements.add(Box::new(Sphere {
-        center: Vector3::new(0.0, 1.0, 6.0),
-        radius: 1.0,
-        shader: red_shader,
-        pitch: 0.0,
-        roll: 0.0,
-        yaw: 0.0,
-    }));
-    elements.add(Box::new(Sphere {
-        center: Vector3::new(2.0, -1.0, 5.0),
-        radius: 1.0,
-        shader: get_phong(Vector3::new(0.0, 1.0, 0.0)),
-        pitch: 0.0,
-        roll: 0.0,
-        yaw: 0.0,
-    }));
-    let mirror: Box<Shader> = Box::new(MirrorShader {
-        initial_step: 0.001,
-    });
-    elements.add(Box::new(Sphere {
-        center: Vector3::new(1.0, 0.0, 7.0),
-        radius: 1.0,
-        shader: mirror + 0.2 * get_phong(Vector3::new(0.0, 0.0, 1.0)),
-        pitch: 0.0,
-        roll: 0.0,
-        yaw: 0.0,
-    }));
-    elements.add(Box::new(Plane {
-        a: Vector3::new(0.0, 1.0, 0.0),
-        b: Vector3::new(1.0, 1.0, 0.0),
-        c: Vector3::new(0.0, 1.0, 1.0),
-        shader: red_blue_shader2,
-    }));
-
-    // add light
-    let mut lights = Vec::new();
-    lights.push(Light::new(0.0, -10.0, 6.0, Vector3::new(1.0, 0.5, 1.0)));
-    lights.push(Light::new(6.0, -10.0, 6.0, Vector3::new(0.5, 1.0, 1.0)));
+    Ok(())
+}
+
+fn save_image(image: DynamicImage, name: &str) -> Result<(), Error> {
+    image
+        .save(name)
+        .map_err(|error| Error::from(format!("Could not save {}: {}", name, error)))
+}
+
+fn camera_from(matches: &ArgMatches) -> Result<Box<Camera>, Error> {
+    let pos = parse_vector(&matches, "pos")?;
+    let roll = parse_value(&matches, "roll")?;
+    let pitch = parse_value(&matches, "pitch")?;
+    let yaw = parse_value(&matches, "yaw")?;
+    let recursion_depth = parse_value(&matches, "depth")?;
+    let height = parse_size(&matches, "height")?;
+    let samples = parse_value(&matches, "samples")?;
+    let sampler = match matches.value_of("sampler").unwrap() {
+        "jittered" => JitteredSampler::new(samples),
+        "halton" => HaltonSampler::new(samples),
+        "sobol" => SobolSampler::new(samples),
+        _ => RegularSampler::new(samples),
+    };
+    let filter = match matches.value_of("filter").unwrap() {
+        "tent" => TentFilter::new(),
+        "gaussian" => GaussianFilter::new(),
+        "mitchell" => MitchellFilter::new(),
+        _ => BoxFilter::new(),
+    };
 
+    if matches.value_of("camera") == Some("equirectangular") {
+        return Ok(Box::new(EquirectangularCamera {
+            height,
+            roll,
+            pitch,
+            yaw,
+            pos,
+            recursion_depth,
+            sampler,
+            filter,
+        }));
+    }
     let mut cam = EquilinearCamera {
-        width: 1200,
-        height: 800,
-        roll: 0.0, // down-up
-        pitch: 3.7, //right-left
-        yaw: 0.0,   //rotation counterclockwise-clockwise
-        pos: Vector3::new(200.0, 0.0, 300.0),
-        vertical_viewangle: 40.0,
+        width: parse_size(&matches, "width")?,
+        height,
+        roll,
+        pitch,
+        yaw,
+        pos,
+        vertical_viewangle: parse_value(&matches, "fov")?,
+        recursion_depth,
+        sampler,
+        filter,
     };
-    let w = World::new(Box::new(PrimitiveStorage { elements: elements.elements }), lights);
-    let image = cam.render(&w, true);
-    let name = format!("output{:?}.png", SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs());
-    image.save(name).expect("Could not save image!");
-    Ok(())
+    if matches.is_present("look-at") {
+        cam.point_at(parse_vector(&matches, "look-at")?);
+    }
+    Ok(Box::new(cam))
+}
+
+/// Background from --background or --sky, an image or the sky lights the scene with
+/// --environment-light
+fn background_from(
+    matches: &ArgMatches,
+    lights: &mut Vec<Box<Light>>,
+) -> Result<Box<Background>, Error> {
+    let samples = if matches.is_present("environment-light") {
+        let samples: u32 = parse_value(matches, "environment-light")?;
+        if samples == 0 {
+            return Err(Error::from("--environment-light needs at least one sample"));
+        }
+        Some(samples)
+    } else {
+        None
+    };
+    if matches.is_present("sky") {
+        let sun_dir = parse_vector(matches, "sky")?;
+        let albedo = Vector3::new(0.2, 0.2, 0.2);
+        let sky = Sky::new(sun_dir, Vector3::new(0.0, -1.0, 0.0), 3.0, albedo, 1.0)?;
+        lights.push(Box::new(sky.sun()));
+        if let Some(samples) = samples {
+            lights.push(Box::new(sky.sky_light(samples)?));
+        }
+        return Ok(Box::new(sky));
+    }
+    let value = match matches.value_of("background") {
+        Some(value) if Path::new(value).is_file() => value,
+        Some(_) if samples.is_none() => {
+            return Ok(SolidBackground::new(parse_vector(matches, "background")?))
+        }
+        None if samples.is_none() => {
+            return Ok(SolidBackground::new(Vector3::new(0.0, 0.0, 0.0)))
+        }
+        _ => {
+            return Err(Error::from(
+                "--environment-light needs a background image or --sky",
+            ))
+        }
+    };
+    let texture = Texture::open(value)?;
+    let map = Arc::new(EnvironmentMap::new(Arc::new(texture), 1.0, 0.0, 0.0, 0.0));
+    if let Some(samples) = samples {
+        lights.push(Box::new(EnvironmentLight::new(map.clone(), samples)));
+    }
+    Ok(Box::new(map))
+}
+
+/// Builder of the bounding volume hierarchy from --storage and the options of the surface area
+/// heuristic, None for a plain list of primitives
+fn builder_from(matches: &ArgMatches) -> Result<Option<Builder>, Error> {
+    let storage = matches.value_of("storage").unwrap();
+    if storage != "sah" {
+        let sah_options = ["sah-bins", "leaf-size", "traversal-cost", "intersection-cost"];
+        if let Some(option) = sah_options.iter().find(|&&option| matches.is_present(option)) {
+            return Err(Error::from(format!("--{} only applies to --storage sah", option)));
+        }
+        return Ok(match storage {
+            "midpoint" => Some(Builder::Midpoint),
+            _ => None,
+        });
+    }
+    let mut parameters = SahParameters::default();
+    if matches.is_present("sah-bins") {
+        parameters.bins = parse_value(matches, "sah-bins")?;
+        if parameters.bins < 2 {
+            return Err(Error::from("--sah-bins needs at least 2 bins"));
+        }
+    }
+    if matches.is_present("leaf-size") {
+        parameters.max_leaf_size = parse_value(matches, "leaf-size")?;
+        if parameters.max_leaf_size == 0 {
+            return Err(Error::from("--leaf-size must be positive"));
+        }
+    }
+    if matches.is_present("traversal-cost") {
+        parameters.traversal_cost = parse_value(matches, "traversal-cost")?;
+        if !parameters.traversal_cost.is_finite() || parameters.traversal_cost < 0.0 {
+            return Err(Error::from("--traversal-cost must be zero or positive"));
+        }
+    }
+    if matches.is_present("intersection-cost") {
+        parameters.intersection_cost = parse_value(matches, "intersection-cost")?;
+        if !parameters.intersection_cost.is_finite() || parameters.intersection_cost <= 0.0 {
+            return Err(Error::from("--intersection-cost must be positive"));
+        }
+    }
+    Ok(Some(Builder::Sah(parameters)))
+}
+
+fn parse_value<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<T, Error> {
+    let value = matches.value_of(name).unwrap();
+    value
+        .parse()
+        .map_err(|_| Error::from(format!("Invalid value for --{}: {}", name, value)))
+}
+
+/// Parse a number of pixels, which must be positive
+fn parse_size(matches: &ArgMatches, name: &str) -> Result<u32, Error> {
+    let size = parse_value(matches, name)?;
+    if size == 0 {
+        return Err(Error::from(format!("--{} must be positive", name)));
+    }
+    Ok(size)
+}
+
+/// Parse an option of the form x,y,z
+fn parse_vector(matches: &ArgMatches, name: &str) -> Result<Vector3<f64>, Error> {
+    let value = matches.value_of(name).unwrap();
+    let coordinates: Vec<f64> = value
+        .split(',')
+        .map(|c| c.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::from(format!("Invalid vector for --{}: {}", name, value)))?;
+    if coordinates.len() != 3 {
+        return Err(Error::from(format!("--{} needs 3 coordinates x,y,z: {}", name, value)));
+    }
+    Ok(Vector3::new(coordinates[0], coordinates[1], coordinates[2]))
 }
@@ -1,3 +1,3 @@
 #!/bin/bash
-echo stripping $1 from complicated f values ...
-sed -i -e 's/\/[0-9]\+//g' -e '/mtllib/d' -e '/usemtl/d' "$1"
\ No newline at end of file
+echo stripping $1 from material references ...
+sed -i -e '/mtllib/d' -e '/usemtl/d' "$1"